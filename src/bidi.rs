@@ -0,0 +1,102 @@
+//! Reordering right-to-left and mixed text for display.
+
+use crate::Terminal;
+use std::io::Write;
+use unicode_bidi::BidiInfo;
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Queues `text` to be written like [`write`](Self::write), but with right-to-left text such as Arabic and Hebrew
+    /// reordered so that it displays correctly, and returns the amount of bytes queued.
+    ///
+    /// `text` is expected in logical order, which is the order in which it is read, and to be on a single line.
+    /// This follows the Unicode Bidirectional Algorithm. Brackets such as parentheses are mirrored in right-to-left text
+    /// so that they still enclose what they did, but other mirrored characters such as mathematical symbols are not.
+    pub fn write_bidi(&mut self, text: &str) -> usize {
+        self.write(&visual_order(text))
+    }
+}
+
+/// Reorders `text` from logical order into the order in which it's displayed from left to right.
+fn visual_order(text: &str) -> String {
+    let info = BidiInfo::new(text, None);
+    let mut visual = String::with_capacity(text.len());
+    for paragraph in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            if levels[run.start].is_rtl() {
+                visual.extend(text[run].chars().rev().map(mirror));
+            } else {
+                visual.push_str(&text[run]);
+            }
+        }
+    }
+    visual
+}
+
+/// Returns the mirrored counterpart of `char` if it is a bracket, or `char` otherwise.
+fn mirror(char: char) -> char {
+    const PAIRS: [(char, char); 8] = [
+        ('(', ')'),
+        ('[', ']'),
+        ('{', '}'),
+        ('<', '>'),
+        ('«', '»'),
+        ('‹', '›'),
+        ('⟨', '⟩'),
+        ('「', '」'),
+    ];
+    PAIRS
+        .iter()
+        .find_map(|&(open, close)| {
+            if char == open {
+                Some(close)
+            } else if char == close {
+                Some(open)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_visual_order() {
+        // Left-to-right text is left as it is
+        assert_eq!(visual_order("abc (def) 123"), "abc (def) 123");
+        // Right-to-left text within left-to-right text
+        assert_eq!(visual_order("abc אבג def"), "abc גבא def");
+        assert_eq!(visual_order("abc אבג דה def"), "abc הד גבא def");
+        // Left-to-right text within right-to-left text
+        assert_eq!(visual_order("אבג abc דה"), "הד abc גבא");
+        // Numbers keep their order
+        assert_eq!(visual_order("אבג 123"), "123 גבא");
+        // Brackets in right-to-left text are mirrored so that they still enclose the same text
+        assert_eq!(visual_order("(אבג)"), "(גבא)");
+        assert_eq!(visual_order("אבג (דה)"), "(הד) גבא");
+        assert_eq!(visual_order("א(ב)ג."), ".ג(ב)א");
+        assert_eq!(visual_order("سلام [عليكم]"), "[مكيلع] مالس");
+        // Brackets in left-to-right text stay as they are
+        assert_eq!(visual_order("abc (אבג) def"), "abc (גבא) def");
+        // Trailing whitespace is at the end of the line, which is on the left in right-to-left text
+        assert_eq!(visual_order("abc אבג "), "abc גבא ");
+        assert_eq!(visual_order("אבג "), " גבא");
+        assert_eq!(visual_order("سلام"), "مالس");
+        // Numbers with separators in Arabic text
+        assert_eq!(visual_order("السعر 1,234.50 دولار"), "رالود 1,234.50 رعسلا");
+    }
+
+    #[test]
+    fn test_write_bidi() {
+        let mut terminal = Terminal::test(Size {
+            width: 80,
+            height: 24,
+        });
+        assert_eq!(terminal.write_bidi("abc אבג"), "abc גבא".len());
+        assert_eq!(terminal.output(), "abc גבא");
+    }
+}
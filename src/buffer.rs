@@ -0,0 +1,257 @@
+//! Grid-based rendering: cells are set in a buffer and only the ones that changed are written when flushing.
+
+use crate::{
+    util::{Attributes, Color, Point, Size},
+    Terminal,
+};
+use std::io::Write;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Cell {
+    pub symbol: char,
+    pub fg: Color,
+    pub bg: Color,
+    pub attributes: Attributes,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: ' ',
+            fg: Color::Gray,
+            bg: Color::Black,
+            attributes: Attributes::empty(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Slot {
+    /// Nothing is known about this cell.
+    Empty,
+    Cell(Cell),
+    /// This cell is covered by the wide character of the cell before it.
+    Continuation,
+}
+
+/// Two grids of cells: the one that has been rendered and the one to be rendered next.
+#[derive(Debug, Default)]
+pub(crate) struct Buffer {
+    size: Size,
+    /// What has been rendered.
+    front: Vec<Slot>,
+    /// What is to be rendered. Empty slots are left as they are.
+    back: Vec<Slot>,
+}
+
+impl Buffer {
+    fn new(size: Size) -> Self {
+        let length = size.product() as usize;
+        Self {
+            size,
+            front: vec![Slot::Empty; length],
+            back: vec![Slot::Empty; length],
+        }
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        if point.x < self.size.width && point.y < self.size.height {
+            Some(point.y as usize * self.size.width as usize + point.x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Forgets what has been rendered, e.g. after the screen has been cleared.
+    pub(crate) fn invalidate(&mut self) {
+        for slot in &mut self.front {
+            *slot = Slot::Empty;
+        }
+    }
+
+    /// Returns what the slot at `point` will look like after the next render.
+    pub(crate) fn get(&self, point: Point) -> Slot {
+        match self.index(point) {
+            Some(index) => match self.back[index] {
+                Slot::Empty => self.front[index],
+                slot => slot,
+            },
+            None => Slot::Empty,
+        }
+    }
+}
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Sets the cell at `point` to `cell`, which is rendered on the next [`flush`](Self::flush)
+    /// unless it's already on the screen.
+    ///
+    /// A wide character also covers the cell after it.
+    /// Points outside of the terminal are ignored.
+    pub fn set_cell(&mut self, point: Point, cell: Cell) {
+        if self.buffer.size != self.size {
+            self.buffer = Buffer::new(self.size);
+        }
+
+        let index = match self.buffer.index(point) {
+            Some(index) => index,
+            None => return,
+        };
+        self.buffer.back[index] = Slot::Cell(cell);
+
+        if cell.symbol.width() == Some(2) {
+            if let Some(index) = self.buffer.index(Point {
+                x: point.x + 1,
+                ..point
+            }) {
+                self.buffer.back[index] = Slot::Continuation;
+            }
+        }
+    }
+
+    /// Returns the symbols of the cells as they will be after the next render, ignoring colors and attributes,
+    /// with a line for each row, e.g. to compare what is drawn to what is expected in tests.
+    ///
+    /// Cells that were never set are spaces and the trailing spaces of each line are left out.
+    /// A wide character takes up the cell after it as well, just like on the screen.
+    pub fn snapshot(&self) -> String {
+        let mut lines = Vec::new();
+        for y in 0..self.size.height {
+            let mut line = String::new();
+            for x in 0..self.size.width {
+                match self.buffer.get(Point { x, y }) {
+                    Slot::Cell(cell) => line.push(cell.symbol),
+                    Slot::Continuation => {}
+                    Slot::Empty => line.push(' '),
+                }
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// Writes the cells that were set and changed since the last render.
+    pub(crate) fn render_buffer(&mut self) {
+        for index in 0..self.buffer.back.len() {
+            let slot = self.buffer.back[index];
+            if slot == Slot::Empty || slot == self.buffer.front[index] {
+                continue;
+            }
+            self.buffer.front[index] = slot;
+
+            if let Slot::Cell(cell) = slot {
+                let width = self.buffer.size.width as usize;
+                let point = Point {
+                    x: (index % width) as u16,
+                    y: (index / width) as u16,
+                };
+                self.move_cursor_to_optimal(point);
+                self.set_foreground_color(cell.fg);
+                self.set_background_color(cell.bg);
+                self.set_attributes(cell.attributes);
+                let mut bytes = [0; 4];
+                self.write(cell.symbol.encode_utf8(&mut bytes));
+            }
+        }
+
+        for slot in &mut self.buffer.back {
+            *slot = Slot::Empty;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_wide_cell() {
+        let mut terminal = Terminal::test(Size {
+            width: 4,
+            height: 2,
+        });
+        let cell = Cell {
+            symbol: '世',
+            ..Cell::default()
+        };
+
+        terminal.set_cell(Point { x: 1, y: 1 }, cell);
+        assert_eq!(terminal.buffer.get(Point { x: 1, y: 1 }), Slot::Cell(cell));
+        assert_eq!(
+            terminal.buffer.get(Point { x: 2, y: 1 }),
+            Slot::Continuation
+        );
+
+        terminal.flush();
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[2;2H\u{1b}[38;5;7m\u{1b}[48;5;0m世"
+        );
+
+        // Nothing changed
+        terminal.set_cell(Point { x: 1, y: 1 }, cell);
+        terminal.flush();
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut terminal = Terminal::test(Size {
+            width: 6,
+            height: 4,
+        });
+        for (y, line) in ["┌──┐", "│世│", "└──┘"].iter().enumerate() {
+            let mut x = 0;
+            for symbol in line.chars() {
+                let cell = Cell {
+                    symbol,
+                    fg: Color::Red,
+                    ..Cell::default()
+                };
+                terminal.set_cell(Point { x, y: y as u16 }, cell);
+                x += symbol.width().unwrap() as u16;
+            }
+        }
+        assert_eq!(terminal.snapshot(), "┌──┐\n│世│\n└──┘\n");
+
+        // What has been rendered is part of the snapshot as well
+        terminal.flush();
+        terminal.set_cell(
+            Point { x: 1, y: 3 },
+            Cell {
+                symbol: 'a',
+                ..Cell::default()
+            },
+        );
+        assert_eq!(terminal.snapshot(), "┌──┐\n│世│\n└──┘\n a");
+    }
+
+    #[test]
+    fn test_render_changed_cells() {
+        let mut terminal = Terminal::test(Size {
+            width: 4,
+            height: 2,
+        });
+        let cell = |symbol| Cell {
+            symbol,
+            ..Cell::default()
+        };
+
+        terminal.set_cell(Point { x: 0, y: 0 }, cell('a'));
+        terminal.set_cell(Point { x: 1, y: 0 }, cell('b'));
+        terminal.set_cell(Point { x: 9, y: 9 }, cell('c'));
+        terminal.flush();
+        assert_eq!(terminal.output(), "\u{1b}[H\u{1b}[38;5;7m\u{1b}[48;5;0mab");
+
+        terminal.set_cell(Point { x: 0, y: 0 }, cell('a'));
+        terminal.set_cell(Point { x: 1, y: 0 }, cell('d'));
+        terminal.flush();
+        assert_eq!(terminal.output(), "\u{1b}[Dd");
+
+        // After clearing, everything has to be rendered again
+        terminal.clear();
+        terminal.set_cell(Point { x: 0, y: 0 }, cell('a'));
+        terminal.flush();
+        assert_eq!(terminal.output(), "\u{1b}[2J\ra");
+    }
+}
@@ -0,0 +1,446 @@
+//! Keeping track of the cursor position so that relative cursor movements can be kept within the terminal.
+
+use crate::{util::Point, OutOfBounds, Terminal};
+use std::io::Write;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Returns the position of the cursor, if known.
+    ///
+    /// The position is known once the cursor has been set using, for example, [`set_cursor`](Self::set_cursor),
+    /// and is kept track of while the cursor is moved and text is written.
+    /// It becomes unknown if text reaches the end of a line, if control characters are written,
+    /// or if something is written using [`Write`] directly.
+    pub fn cursor_point(&self) -> Option<Point> {
+        self.cursor
+    }
+
+    /// Returns how many cells the cursor can move in `direction` without leaving the terminal.
+    ///
+    /// If the cursor position is unknown, this is how many cells it can move from the opposite edge.
+    fn cursor_room(&self, direction: Direction) -> u16 {
+        let max = Point {
+            x: self.size.width.saturating_sub(1),
+            y: self.size.height.saturating_sub(1),
+        };
+        match (direction, self.cursor) {
+            (Direction::Up, Some(cursor)) => cursor.y,
+            (Direction::Down, Some(cursor)) => max.y.saturating_sub(cursor.y),
+            (Direction::Left, Some(cursor)) => cursor.x,
+            (Direction::Right, Some(cursor)) => max.x.saturating_sub(cursor.x),
+            (Direction::Up | Direction::Down, None) => max.y,
+            (Direction::Left | Direction::Right, None) => max.x,
+        }
+    }
+
+    /// Clamps a movement of the cursor by `cells` in `direction` so that it stays within the terminal,
+    /// keeps track of it and returns by how many cells the cursor is to be moved.
+    pub(crate) fn clamp_cursor_move(&mut self, direction: Direction, cells: u16) -> u16 {
+        let cells = cells.min(self.cursor_room(direction));
+        if let Some(cursor) = &mut self.cursor {
+            match direction {
+                Direction::Up => cursor.y -= cells,
+                Direction::Down => cursor.y += cells,
+                Direction::Left => cursor.x -= cells,
+                Direction::Right => cursor.x += cells,
+            }
+        }
+        cells
+    }
+
+    /// Keeps track of the cursor moving to the start of the next or previous line.
+    pub(crate) fn track_line_move(&mut self, direction: Direction) {
+        self.clamp_cursor_move(direction, 1);
+        if let Some(cursor) = &mut self.cursor {
+            cursor.x = 0;
+        }
+    }
+
+    /// Keeps track of the cursor moving past `bytes` written as text.
+    pub(crate) fn track_text(&mut self, bytes: &[u8]) {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => {
+                self.cursor = None;
+                return;
+            }
+        };
+
+        let mut x = cursor.x as usize;
+        for char in text.chars() {
+            match char.width() {
+                Some(width) => x += width,
+                // A control character
+                None => {
+                    self.cursor = None;
+                    return;
+                }
+            }
+        }
+
+        // Where the cursor ends up at the end of a line differs between terminals
+        self.cursor = if x < self.size.width as usize {
+            Some(Point {
+                x: x as u16,
+                ..cursor
+            })
+        } else {
+            None
+        };
+    }
+
+    /// Returns where the cursor would end up if `text` was written starting at `start`, without writing anything.
+    ///
+    /// Characters advance the cursor by their display width and lines wrap at the terminal's width.
+    /// A wide character that doesn't fit on the rest of a line is moved to the next line and
+    /// text that reaches the end of a line leaves the cursor at the start of the next line.
+    /// If line wrapping is disabled using [`disable_line_wrap`](Self::disable_line_wrap),
+    /// the cursor stays in the last column instead.
+    /// `\r` returns to the start of the line and `\n` moves to the next line, in raw mode without returning to the start.
+    /// Lines beyond the bottom scroll the screen, so the cursor stays on the last line.
+    pub fn measure(&self, text: &str, start: Point) -> Point {
+        let width = self.size.width.max(1) as usize;
+        let (mut x, mut y) = (start.x as usize, start.y as usize);
+        for char in text.chars() {
+            match char {
+                '\r' => x = 0,
+                '\n' => {
+                    y += 1;
+                    if !self.raw_mode {
+                        x = 0;
+                    }
+                }
+                '\t' => x = ((x / 8 + 1) * 8).min(width - 1),
+                char => {
+                    let char_width = char.width().unwrap_or(0);
+                    if !self.line_wrap {
+                        x = (x + char_width).min(width - 1);
+                        continue;
+                    }
+                    if x + char_width > width {
+                        x = 0;
+                        y += 1;
+                    }
+                    x += char_width;
+                    if x >= width {
+                        x = 0;
+                        y += 1;
+                    }
+                }
+            }
+        }
+        Point {
+            x: x as u16,
+            y: y.min(self.size.height.saturating_sub(1) as usize) as u16,
+        }
+    }
+
+    /// Moves the cursor to `point` using the shortest sequence available.
+    ///
+    /// If the cursor position is known, relative movements, carriage returns and line feeds are considered
+    /// besides setting the position directly, which is all [`set_cursor`](Self::set_cursor) does.
+    /// Nothing is written if the cursor is already at `point`.
+    pub fn move_cursor_to_optimal(&mut self, point: Point) {
+        let sequence = optimal_cursor_move(self.cursor, point);
+        if !sequence.is_empty() {
+            self.write_escape(&sequence);
+        }
+        self.cursor = Some(point);
+    }
+
+    /// Returns an error if the cursor can't move by `cells` in `direction` without leaving the terminal.
+    fn check_cursor_move(&self, direction: Direction, cells: u16) -> Result<(), OutOfBounds> {
+        if cells <= self.cursor_room(direction) {
+            Ok(())
+        } else {
+            Err(OutOfBounds)
+        }
+    }
+
+    /// Moves the cursor up by `cells` like [`move_cursor_up_by`](Self::move_cursor_up_by),
+    /// but instead of stopping at the top, nothing is done and an error is returned if it would be passed.
+    ///
+    /// If the cursor position is unknown, it's only an error to move by at least the terminal's height.
+    pub fn try_move_cursor_up_by(&mut self, cells: u16) -> Result<(), OutOfBounds> {
+        self.check_cursor_move(Direction::Up, cells)?;
+        self.move_cursor_up_by(cells);
+        Ok(())
+    }
+    /// Moves the cursor down by `cells` like [`move_cursor_down_by`](Self::move_cursor_down_by),
+    /// but instead of stopping at the bottom, nothing is done and an error is returned if it would be passed.
+    ///
+    /// If the cursor position is unknown, it's only an error to move by at least the terminal's height.
+    pub fn try_move_cursor_down_by(&mut self, cells: u16) -> Result<(), OutOfBounds> {
+        self.check_cursor_move(Direction::Down, cells)?;
+        self.move_cursor_down_by(cells);
+        Ok(())
+    }
+    /// Moves the cursor left by `cells` like [`move_cursor_left_by`](Self::move_cursor_left_by),
+    /// but instead of stopping at the left edge, nothing is done and an error is returned if it would be passed.
+    ///
+    /// If the cursor position is unknown, it's only an error to move by at least the terminal's width.
+    pub fn try_move_cursor_left_by(&mut self, cells: u16) -> Result<(), OutOfBounds> {
+        self.check_cursor_move(Direction::Left, cells)?;
+        self.move_cursor_left_by(cells);
+        Ok(())
+    }
+    /// Moves the cursor right by `cells` like [`move_cursor_right_by`](Self::move_cursor_right_by),
+    /// but instead of stopping at the right edge, nothing is done and an error is returned if it would be passed.
+    ///
+    /// If the cursor position is unknown, it's only an error to move by at least the terminal's width.
+    pub fn try_move_cursor_right_by(&mut self, cells: u16) -> Result<(), OutOfBounds> {
+        self.check_cursor_move(Direction::Right, cells)?;
+        self.move_cursor_right_by(cells);
+        Ok(())
+    }
+}
+
+/// Returns the shortest sequence moving the cursor from `from`, if known, to `to`.
+fn optimal_cursor_move(from: Option<Point>, to: Point) -> String {
+    let absolute = match to {
+        Point { x: 0, y: 0 } => "\u{1b}[H".to_string(),
+        Point { x: 0, y } => format!("\u{1b}[{}H", y + 1),
+        Point { x, y } => format!("\u{1b}[{};{}H", y + 1, x + 1),
+    };
+    let from = match from {
+        Some(from) => from,
+        None => return absolute,
+    };
+
+    let vertical = if to.y < from.y {
+        relative_cursor_move(from.y - to.y, 'A')
+    } else {
+        relative_cursor_move(to.y - from.y, 'B')
+    };
+    let horizontal = if to.x < from.x {
+        relative_cursor_move(from.x - to.x, 'D')
+    } else {
+        relative_cursor_move(to.x - from.x, 'C')
+    };
+    let column = format!("\u{1b}[{}G", to.x + 1);
+
+    let mut candidates = vec![vertical.clone() + &shortest([horizontal, column])];
+    if to.x == 0 {
+        // Line feeds don't scroll here because the cursor stays within the terminal,
+        // and they don't depend on whether they are translated to CRLF
+        let vertical = if to.y > from.y {
+            shortest([vertical, "\n".repeat((to.y - from.y) as usize)])
+        } else {
+            vertical
+        };
+        candidates.push("\r".to_string() + &vertical);
+    }
+    candidates.push(absolute);
+    shortest(candidates)
+}
+
+/// Returns the sequence moving the cursor by `cells` using the CSI sequence ending in `direction`.
+fn relative_cursor_move(cells: u16, direction: char) -> String {
+    match cells {
+        0 => String::new(),
+        1 => format!("\u{1b}[{}", direction),
+        cells => format!("\u{1b}[{}{}", cells, direction),
+    }
+}
+
+/// Returns the shortest of `sequences`, preferring earlier ones.
+fn shortest(sequences: impl IntoIterator<Item = String>) -> String {
+    sequences.into_iter().min_by_key(String::len).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{Color, Size};
+
+    fn terminal() -> Terminal<'static, Vec<u8>> {
+        Terminal::test(Size {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut terminal = Terminal::test(Size {
+            width: 10,
+            height: 3,
+        });
+        assert_eq!(
+            terminal.measure("abc", Point { x: 1, y: 0 }),
+            Point { x: 4, y: 0 }
+        );
+        // The wide character doesn't fit in the last column
+        assert_eq!(
+            terminal.measure("ab世", Point { x: 7, y: 0 }),
+            Point { x: 2, y: 1 }
+        );
+        assert_eq!(
+            terminal.measure("世界", Point { x: 6, y: 1 }),
+            Point { x: 0, y: 2 }
+        );
+        // The screen scrolls at the bottom
+        assert_eq!(
+            terminal.measure("abcdefghijklmnopqrstuvwxyz", Point { x: 0, y: 1 }),
+            Point { x: 6, y: 2 }
+        );
+
+        assert_eq!(
+            terminal.measure("ab\ncd", Point { x: 3, y: 0 }),
+            Point { x: 2, y: 1 }
+        );
+        assert_eq!(
+            terminal.measure("abc\rd\te", Point { x: 3, y: 0 }),
+            Point { x: 9, y: 0 }
+        );
+        terminal.raw_mode = true;
+        assert_eq!(
+            terminal.measure("ab\ncd", Point { x: 3, y: 0 }),
+            Point { x: 7, y: 1 }
+        );
+        // Nothing is written
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_measure_without_line_wrap() {
+        let mut terminal = Terminal::test(Size {
+            width: 10,
+            height: 3,
+        });
+        terminal.disable_line_wrap();
+        assert_eq!(terminal.output(), "\u{1b}[?7l");
+
+        // Text beyond the end of the line overwrites the last column
+        assert_eq!(
+            terminal.measure("abcdefghijklmnop", Point { x: 0, y: 0 }),
+            Point { x: 9, y: 0 }
+        );
+        assert_eq!(
+            terminal.measure("ab世", Point { x: 7, y: 1 }),
+            Point { x: 9, y: 1 }
+        );
+        assert_eq!(
+            terminal.measure("abcdefghijkl\ncd", Point { x: 0, y: 0 }),
+            Point { x: 2, y: 1 }
+        );
+
+        terminal.enable_line_wrap();
+        assert_eq!(terminal.output(), "\u{1b}[?7h");
+        assert_eq!(
+            terminal.measure("abcdefghijklmnop", Point { x: 0, y: 0 }),
+            Point { x: 6, y: 1 }
+        );
+
+        // A reset enables it again
+        terminal.disable_line_wrap();
+        terminal.hard_reset();
+        assert_eq!(
+            terminal.measure("abcdefghijklmnop", Point { x: 0, y: 0 }),
+            Point { x: 6, y: 1 }
+        );
+    }
+
+    #[test]
+    fn test_clamped_cursor_moves() {
+        let mut terminal = terminal();
+        terminal.set_cursor(Point { x: 2, y: 3 });
+        terminal.output();
+
+        terminal.move_cursor_up_by(1000);
+        assert_eq!(terminal.output(), "\u{1b}[3A");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 2, y: 0 }));
+        terminal.move_cursor_up_by(1);
+        assert_eq!(terminal.output(), "");
+
+        terminal.move_cursor_left_by(1000);
+        assert_eq!(terminal.output(), "\u{1b}[2D");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 0 }));
+        // Moving left at the left edge doesn't wrap
+        terminal.move_cursor_left_by(1);
+        assert_eq!(terminal.output(), "");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 0 }));
+
+        terminal.move_cursor_down_by(1000);
+        assert_eq!(terminal.output(), "\u{1b}[23B");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 23 }));
+
+        terminal.move_cursor_right_by(1000);
+        assert_eq!(terminal.output(), "\u{1b}[79C");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 79, y: 23 }));
+        terminal.move_cursor_right_by(1);
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_try_cursor_moves() {
+        let mut terminal = terminal();
+        terminal.set_cursor(Point { x: 2, y: 3 });
+        terminal.output();
+
+        assert!(terminal.try_move_cursor_up_by(4).is_err());
+        assert!(terminal.try_move_cursor_left_by(3).is_err());
+        assert_eq!(terminal.output(), "");
+        assert!(terminal.try_move_cursor_up_by(3).is_ok());
+        assert!(terminal.try_move_cursor_down_by(23).is_ok());
+        assert!(terminal.try_move_cursor_down_by(1).is_err());
+        assert!(terminal.try_move_cursor_right_by(78).is_err());
+        assert!(terminal.try_move_cursor_right_by(77).is_ok());
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 79, y: 23 }));
+    }
+
+    #[test]
+    fn test_move_cursor_to_optimal() {
+        let mut terminal = terminal();
+        let point = |x, y| Point { x, y };
+
+        for (from, to, expected) in [
+            (None, point(0, 0), "\u{1b}[H"),
+            (None, point(0, 4), "\u{1b}[5H"),
+            (None, point(4, 2), "\u{1b}[3;5H"),
+            (Some(point(5, 5)), point(5, 5), ""),
+            (Some(point(5, 5)), point(6, 5), "\u{1b}[C"),
+            (Some(point(5, 5)), point(15, 5), "\u{1b}[10C"),
+            (Some(point(50, 5)), point(2, 5), "\u{1b}[3G"),
+            (Some(point(5, 5)), point(5, 9), "\u{1b}[4B"),
+            (Some(point(5, 5)), point(4, 4), "\u{1b}[A\u{1b}[D"),
+            (Some(point(5, 5)), point(0, 5), "\r"),
+            (Some(point(5, 5)), point(0, 6), "\r\n"),
+            (Some(point(5, 5)), point(0, 7), "\r\n\n"),
+            (Some(point(5, 5)), point(0, 2), "\u{1b}[3H"),
+            (Some(point(5, 20)), point(0, 19), "\r\u{1b}[A"),
+            (Some(point(70, 20)), point(3, 1), "\u{1b}[2;4H"),
+        ] {
+            terminal.cursor = from;
+            terminal.move_cursor_to_optimal(to);
+            assert_eq!(terminal.output(), expected, "from {:?} to {}", from, to);
+            assert_eq!(terminal.cursor_point(), Some(to));
+        }
+    }
+
+    #[test]
+    fn test_track_text() {
+        let mut terminal = terminal();
+        assert_eq!(terminal.cursor_point(), None);
+        terminal.set_cursor(Point { x: 1, y: 1 });
+        terminal.write("a世");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 4, y: 1 }));
+        terminal.set_foreground_color(Color::Red);
+        terminal.next_line();
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 2 }));
+        terminal.write("a\n");
+        assert_eq!(terminal.cursor_point(), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! Drawing lines and boxes out of characters.
+
+use crate::{
+    util::{BoxStyle, Point, Rect},
+    Terminal,
+};
+use std::io::Write;
+use unicode_width::UnicodeWidthChar;
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Draws a line of `length` cells made of `char` from `start` to the right.
+    ///
+    /// The cursor is moved only once and the line is cut off at the edge of the terminal.
+    /// If the terminal understands REP (`CSI n b`) and it's shorter, `char` is written once and then repeated
+    /// using it. See [`Capabilities::rep`](crate::util::Capabilities::rep).
+    pub fn draw_horizontal_line(&mut self, start: Point, length: u16, char: char) {
+        if start.y >= self.size.height {
+            return;
+        }
+        let width = char_width(char);
+        let length = length.min(self.size.width.saturating_sub(start.x));
+        let count = length / width;
+        if count == 0 {
+            return;
+        }
+        self.move_cursor_to_optimal(start);
+
+        let repeat = format!("\u{1b}[{}b", count - 1);
+        let repeated_bytes = (count as usize - 1) * char.len_utf8();
+        let capabilities = self.capabilities();
+        if capabilities.escapes && capabilities.rep && repeat.len() < repeated_bytes {
+            let mut bytes = [0; 4];
+            self.write(char.encode_utf8(&mut bytes));
+            self.write_escape(&repeat);
+            // The cursor ends up at the end of the line like after writing the characters
+            let end = start.x as usize + (count * width) as usize;
+            self.cursor = if end < self.size.width as usize {
+                Some(Point {
+                    x: end as u16,
+                    ..start
+                })
+            } else {
+                None
+            };
+        } else {
+            let line: String = std::iter::repeat_n(char, count as usize).collect();
+            self.write(&line);
+        }
+    }
+
+    /// Draws a line of `length` cells made of `char` from `start` downwards.
+    ///
+    /// The line is cut off at the edge of the terminal.
+    pub fn draw_vertical_line(&mut self, start: Point, length: u16, char: char) {
+        if start.x.saturating_add(char_width(char)) > self.size.width {
+            return;
+        }
+        let length = length.min(self.size.height.saturating_sub(start.y));
+        let mut bytes = [0; 4];
+        let char = char.encode_utf8(&mut bytes);
+        for y in start.y..start.y + length {
+            self.move_cursor_to_optimal(Point { y, ..start });
+            self.write(char);
+        }
+    }
+
+    /// Draws the outline of `rect` using the box-drawing characters of `style`.
+    ///
+    /// The box is cut off at the edge of the terminal.
+    pub fn draw_box(&mut self, rect: Rect, style: BoxStyle) {
+        let Rect { point, size } = rect;
+        let [horizontal, vertical, top_left, top_right, bottom_left, bottom_right] = style.chars();
+        match (size.width, size.height) {
+            (0, _) | (_, 0) => return,
+            (_, 1) => return self.draw_horizontal_line(point, size.width, horizontal),
+            (1, _) => return self.draw_vertical_line(point, size.height, vertical),
+            _ => {}
+        }
+
+        let right = point.x.saturating_add(size.width - 1);
+        let bottom = point.y.saturating_add(size.height - 1);
+        let inner = Point {
+            x: point.x.saturating_add(1),
+            y: point.y.saturating_add(1),
+        };
+
+        for (y, left_corner, right_corner) in [
+            (point.y, top_left, top_right),
+            (bottom, bottom_left, bottom_right),
+        ] {
+            self.draw_horizontal_line(Point { x: point.x, y }, 1, left_corner);
+            self.draw_horizontal_line(Point { x: inner.x, y }, size.width - 2, horizontal);
+            self.draw_horizontal_line(Point { x: right, y }, 1, right_corner);
+        }
+        self.draw_vertical_line(
+            Point {
+                x: point.x,
+                y: inner.y,
+            },
+            size.height - 2,
+            vertical,
+        );
+        self.draw_vertical_line(
+            Point {
+                x: right,
+                y: inner.y,
+            },
+            size.height - 2,
+            vertical,
+        );
+    }
+}
+
+/// Returns how many cells `char` takes up, assuming at least one.
+fn char_width(char: char) -> u16 {
+    char.width().unwrap_or(1).max(1) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{Capabilities, Size};
+
+    fn terminal() -> Terminal<'static, Vec<u8>> {
+        Terminal::test(Size {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    #[test]
+    fn test_draw_horizontal_line() {
+        let mut terminal = terminal();
+
+        terminal.draw_horizontal_line(Point { x: 2, y: 3 }, 5, '─');
+        assert_eq!(terminal.output(), "\u{1b}[4;3H─────");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 7, y: 3 }));
+
+        // The line stays within the terminal
+        terminal.draw_horizontal_line(Point { x: 77, y: 0 }, 5, '─');
+        assert_eq!(terminal.output(), "\u{1b}[1;78H───");
+        terminal.draw_horizontal_line(Point { x: 0, y: 24 }, 5, '─');
+        terminal.draw_horizontal_line(Point { x: 80, y: 0 }, 5, '─');
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_draw_horizontal_line_rep() {
+        let mut terminal = terminal();
+        terminal.force_capabilities(Capabilities {
+            rep: true,
+            ..terminal.capabilities()
+        });
+
+        // The character is repeated using REP where it's shorter
+        terminal.draw_horizontal_line(Point { x: 2, y: 3 }, 5, '─');
+        assert_eq!(terminal.output(), "\u{1b}[4;3H─\u{1b}[4b");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 7, y: 3 }));
+        terminal.draw_horizontal_line(Point { x: 0, y: 4 }, 2, '─');
+        assert_eq!(terminal.output(), "\r\n──");
+        terminal.draw_horizontal_line(Point { x: 0, y: 5 }, 80, '=');
+        assert_eq!(terminal.output(), "\r\n=\u{1b}[79b");
+        assert_eq!(terminal.cursor_point(), None);
+        terminal.draw_horizontal_line(Point { x: 77, y: 0 }, 5, '─');
+        assert_eq!(terminal.output(), "\u{1b}[1;78H─\u{1b}[2b");
+
+        // Without escape sequences, the characters are written as they are
+        terminal.set_cursor(Point { x: 2, y: 3 });
+        terminal.output();
+        terminal.set_escape_suppression(true);
+        terminal.draw_horizontal_line(Point { x: 2, y: 3 }, 5, '─');
+        assert_eq!(terminal.output(), "─────");
+    }
+
+    #[test]
+    fn test_draw_vertical_line() {
+        let mut terminal = terminal();
+
+        terminal.draw_vertical_line(Point { x: 2, y: 21 }, 5, '│');
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[22;3H│\u{1b}[B\u{1b}[D│\u{1b}[B\u{1b}[D│"
+        );
+    }
+
+    #[test]
+    fn test_draw_box() {
+        let mut terminal = terminal();
+
+        let rect = Rect {
+            point: Point { x: 0, y: 0 },
+            size: Size {
+                width: 4,
+                height: 3,
+            },
+        };
+        terminal.draw_box(rect, BoxStyle::Rounded);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[H╭──╮\r\n\n╰──╯\r\u{1b}[A│\u{1b}[2C│"
+        );
+
+        terminal.draw_box(
+            Rect {
+                size: Size {
+                    width: 3,
+                    height: 1,
+                },
+                ..rect
+            },
+            BoxStyle::Double,
+        );
+        assert_eq!(terminal.output(), "\u{1b}[H═══");
+    }
+}
@@ -1,15 +1,16 @@
 //! Terminal events defined specific to usage.
 
-use crate::util::Point;
+use crate::util::{Point, Size};
+use std::fmt;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MouseEventKind {
     ScrollUp,
     ScrollDown,
@@ -19,7 +20,7 @@ pub enum MouseEventKind {
     Release(MouseButton),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Key {
     Char(char),
     // Alt(char),
@@ -29,36 +30,452 @@ pub enum Key {
     Left,
     Right,
     Tab,
+    /// Shift+Tab.
+    BackTab,
     Enter,
+    /// A function key, e.g. `F(1)` for F1.
+    ///
+    /// Note that, following a convention of xterm and others, some terminals report function keys pressed together
+    /// with a modifier as higher function keys instead: Shift+F1 to Shift+F12 are commonly reported as F13 to F24,
+    /// so that F17 may actually be Shift+F5.
     F(u8),
     Backspace,
     Esc,
+    /// A key on the numeric keypad.
+    ///
+    /// This is only reported if the terminal distinguishes the keypad from the other keys,
+    /// which is the case if application keypad mode or the Kitty keyboard protocol is enabled.
+    /// Otherwise keypad keys are reported like their counterparts on the main keyboard, e.g. as [`Key::Char`].
+    Keypad(KeypadKey),
 }
 
-// #[derive(Debug)]
-// pub struct KeyEvent {
-//     pub key: Key,
-//     pub modifier: Option<KeyModifier>,
-// }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeypadKey {
+    /// A digit from 0 to 9.
+    Digit(u8),
+    Enter,
+    /// The `+` key.
+    Add,
+    /// The `-` key.
+    Subtract,
+    /// The `*` key.
+    Multiply,
+    /// The `/` key.
+    Divide,
+    /// The `.` key.
+    Decimal,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum KeyEventKind {
+    #[default]
+    Press,
+    /// The key is being held down.
+    Repeat,
+    Release,
+}
+
+/// A key event.
+///
+/// Note that repeats and releases are only reported if requested using [`crate::Terminal::set_report_key_repeats`]
+/// and [`crate::Terminal::set_report_key_releases`]. Additionally, on Unix-like operating systems,
+/// the Kitty keyboard protocol must be enabled using [`crate::Terminal::enable_kitty_keyboard`].
+/// Otherwise only presses are reported.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub kind: KeyEventKind,
+    /// The modifiers held down together with the key.
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyEvent {
+    /// Parses a key press as it is displayed, such as `Ctrl+c`, `Alt+Left`, `F5` or `Shift+Tab`,
+    /// for example for configurable key bindings.
+    ///
+    /// The names of keys and modifiers are case-insensitive, but characters are not.
+    /// `Shift+Tab` and `BackTab` are both parsed as [`Key::BackTab`] with Shift, which is how terminals report them.
+    /// Returns `None` if a name is unknown.
+    pub fn parse(string: &str) -> Option<KeyEvent> {
+        let mut parts = string.rsplit('+');
+        let key = Key::parse(parts.next()?)?;
+
+        let mut modifiers = KeyModifiers::empty();
+        for part in parts {
+            let modifier = match part.to_ascii_lowercase().as_str() {
+                "shift" => KeyModifier::Shift,
+                "alt" => KeyModifier::Alt,
+                "ctrl" | "control" => KeyModifier::Control,
+                _ => return None,
+            };
+            modifiers.insert(modifier);
+        }
+
+        // Terminals report Shift+Tab as BackTab together with Shift
+        let key = match key {
+            Key::Tab if modifiers.contains(KeyModifier::Shift) => Key::BackTab,
+            key => key,
+        };
+        if key == Key::BackTab {
+            modifiers.insert(KeyModifier::Shift);
+        }
+
+        Some(KeyEvent {
+            modifiers,
+            ..key.into()
+        })
+    }
+}
+
+impl Key {
+    /// Parses a key as it is displayed. See [`KeyEvent::parse`].
+    fn parse(string: &str) -> Option<Key> {
+        let mut chars = string.chars();
+        if let (Some(char), None) = (chars.next(), chars.next()) {
+            return Some(Key::Char(char));
+        }
+
+        let lowercase = string.to_ascii_lowercase();
+        Some(match lowercase.as_str() {
+            "space" => Key::Char(' '),
+            "plus" => Key::Char('+'),
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "tab" => Key::Tab,
+            "backtab" => Key::BackTab,
+            "enter" => Key::Enter,
+            "backspace" => Key::Backspace,
+            "esc" | "escape" => Key::Esc,
+            "keypadenter" => Key::Keypad(KeypadKey::Enter),
+            "keypadadd" => Key::Keypad(KeypadKey::Add),
+            "keypadsubtract" => Key::Keypad(KeypadKey::Subtract),
+            "keypadmultiply" => Key::Keypad(KeypadKey::Multiply),
+            "keypaddivide" => Key::Keypad(KeypadKey::Divide),
+            "keypaddecimal" => Key::Keypad(KeypadKey::Decimal),
+            name => {
+                if let Some(digit) = name.strip_prefix("keypad") {
+                    match digit.parse() {
+                        Ok(digit @ 0..=9) => Key::Keypad(KeypadKey::Digit(digit)),
+                        _ => return None,
+                    }
+                } else {
+                    Key::F(name.strip_prefix('f')?.parse().ok()?)
+                }
+            }
+        })
+    }
+}
+
+impl From<Key> for KeyEvent {
+    fn from(key: Key) -> Self {
+        Self {
+            key,
+            kind: KeyEventKind::default(),
+            modifiers: KeyModifiers::empty(),
+        }
+    }
+}
+
+/// A modifier key.
+///
+/// Note that terminals can't report all combinations of modifiers and keys.
+/// For example, Control and a letter is often indistinguishable from Control, Shift and that letter.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum KeyModifier {
+    Shift,
+    Alt,
+    Control,
+}
+
+impl KeyModifier {
+    pub const ALL: [KeyModifier; 3] = [KeyModifier::Shift, KeyModifier::Alt, KeyModifier::Control];
+}
+
+/// A set of modifier keys.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    fn bit(modifier: KeyModifier) -> u8 {
+        1 << modifier as u8
+    }
+
+    pub fn contains(&self, modifier: KeyModifier) -> bool {
+        self.0 & Self::bit(modifier) != 0
+    }
+
+    pub fn insert(&mut self, modifier: KeyModifier) {
+        self.0 |= Self::bit(modifier);
+    }
+
+    pub fn remove(&mut self, modifier: KeyModifier) {
+        self.0 &= !Self::bit(modifier);
+    }
 
-// #[derive(Debug)]
-// pub enum KeyModifier {
-//     Shift,
-//     Control,
-//     Alt,
-// }
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
 
-#[derive(Clone, Copy, Debug)]
+    pub fn iter(&self) -> impl Iterator<Item = KeyModifier> + '_ {
+        KeyModifier::ALL
+            .iter()
+            .copied()
+            .filter(move |modifier| self.contains(*modifier))
+    }
+}
+
+impl From<&[KeyModifier]> for KeyModifiers {
+    fn from(modifiers: &[KeyModifier]) -> Self {
+        let mut set = Self::empty();
+        for modifier in modifiers {
+            set.insert(*modifier);
+        }
+        set
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct MouseEvent {
     pub kind: MouseEventKind,
     pub point: Point,
+    /// The position in pixels, if mouse positions are reported in pixels.
+    /// See [`crate::Terminal::enable_mouse_pixels`].
+    pub pixel: Option<Point>,
     // TODO: modifier: Option<KeyModifier> (or bitflags for multipl events)
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Event {
-    Key(Key),
+    Key(KeyEvent),
     Mouse(MouseEvent),
-    /// No `Size` included. Call [`crate::Terminal::size`] instead.
-    Resize,
+    /// The terminal has been resized from `old` to `new`, which is also what [`crate::Terminal::size`] returns now.
+    Resize {
+        old: Size,
+        new: Size,
+    },
+    /// Input that doesn't map to any of the other events, described for debugging purposes.
+    ///
+    /// With the `minimal-unix` backend, this is the input itself with non-printable bytes escaped, e.g. `\x1b[42~`.
+    /// With crossterm, this is crossterm's description of the event.
+    ///
+    /// This is only reported if enabled using [`set_report_unknown_events`](crate::Terminal::set_report_unknown_events).
+    Unknown(String),
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Displays the mouse event like `MousePress(Left)@(3, 4)`.
+impl fmt::Display for MouseEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            MouseEventKind::ScrollUp => write!(f, "MouseScrollUp")?,
+            MouseEventKind::ScrollDown => write!(f, "MouseScrollDown")?,
+            MouseEventKind::Move => write!(f, "MouseMove")?,
+            MouseEventKind::Drag(button) => write!(f, "MouseDrag({})", button)?,
+            MouseEventKind::Press(button) => write!(f, "MousePress({})", button)?,
+            MouseEventKind::Release(button) => write!(f, "MouseRelease({})", button)?,
+        }
+        write!(f, "@{}", self.point)
+    }
+}
+
+/// Displays the key like `Enter`, `F5` or `a`.
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Char(' ') => write!(f, "Space"),
+            // `+` separates modifiers and keys
+            Key::Char('+') => write!(f, "Plus"),
+            Key::Char(char) => write!(f, "{}", char),
+            Key::F(number) => write!(f, "F{}", number),
+            Key::Keypad(KeypadKey::Digit(digit)) => write!(f, "Keypad{}", digit),
+            Key::Keypad(key) => write!(f, "Keypad{:?}", key),
+            key => fmt::Debug::fmt(key, f),
+        }
+    }
+}
+
+impl fmt::Display for KeyModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyModifier::Shift => write!(f, "Shift"),
+            KeyModifier::Alt => write!(f, "Alt"),
+            KeyModifier::Control => write!(f, "Ctrl"),
+        }
+    }
+}
+
+/// Displays the modifiers like `Ctrl+Shift`.
+impl fmt::Display for KeyModifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut modifiers = [KeyModifier::Control, KeyModifier::Alt, KeyModifier::Shift]
+            .iter()
+            .filter(|modifier| self.contains(**modifier));
+        if let Some(modifier) = modifiers.next() {
+            write!(f, "{}", modifier)?;
+        }
+        for modifier in modifiers {
+            write!(f, "+{}", modifier)?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays the key event like `Ctrl+Shift+F5`. Repeats and releases are followed by `(repeat)` and `(release)`.
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.modifiers.is_empty() {
+            write!(f, "{}+", self.modifiers)?;
+        }
+        write!(f, "{}", self.key)?;
+        match self.kind {
+            KeyEventKind::Press => Ok(()),
+            KeyEventKind::Repeat => write!(f, " (repeat)"),
+            KeyEventKind::Release => write!(f, " (release)"),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Key(event) => write!(f, "{}", event),
+            Event::Mouse(event) => write!(f, "{}", event),
+            Event::Resize { old, new } => write!(
+                f,
+                "Resize({}x{} -> {}x{})",
+                old.width, old.height, new.width, new.height
+            ),
+            Event::Unknown(description) => write!(f, "Unknown({})", description),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_events() {
+        let ctrl_c = KeyEvent {
+            modifiers: KeyModifiers::from(&[KeyModifier::Control][..]),
+            ..Key::Char('c').into()
+        };
+        assert_eq!(Event::Key(ctrl_c).to_string(), "Ctrl+c");
+        assert_eq!(
+            KeyEvent {
+                kind: KeyEventKind::Release,
+                modifiers: KeyModifiers::from(&[KeyModifier::Shift, KeyModifier::Control][..]),
+                ..Key::F(5).into()
+            }
+            .to_string(),
+            "Ctrl+Shift+F5 (release)"
+        );
+        assert_eq!(KeyEvent::from(Key::Char(' ')).to_string(), "Space");
+        let back_tab = KeyEvent::parse("BackTab").unwrap();
+        assert_eq!(back_tab.to_string(), "Shift+BackTab");
+        assert_eq!(KeyEvent::parse(&back_tab.to_string()), Some(back_tab));
+        assert_eq!(
+            KeyEvent::from(Key::Keypad(KeypadKey::Digit(5))).to_string(),
+            "Keypad5"
+        );
+        assert_eq!(
+            KeyEvent::from(Key::Keypad(KeypadKey::Enter)).to_string(),
+            "KeypadEnter"
+        );
+        assert_eq!(
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Press(MouseButton::Left),
+                point: Point { x: 3, y: 4 },
+                pixel: None,
+            })
+            .to_string(),
+            "MousePress(Left)@(3, 4)"
+        );
+        assert_eq!(
+            Event::Resize {
+                old: Size {
+                    width: 80,
+                    height: 24
+                },
+                new: Size {
+                    width: 100,
+                    height: 50
+                }
+            }
+            .to_string(),
+            "Resize(80x24 -> 100x50)"
+        );
+    }
+
+    #[test]
+    fn test_parse_key_event() {
+        let parse = |string| KeyEvent::parse(string).map(|event| (event.key, event.modifiers));
+        let modifiers = |modifiers: &[KeyModifier]| KeyModifiers::from(modifiers);
+
+        assert_eq!(parse("F5"), Some((Key::F(5), modifiers(&[]))));
+        assert_eq!(parse("enter"), Some((Key::Enter, modifiers(&[]))));
+        assert_eq!(parse("a"), Some((Key::Char('a'), modifiers(&[]))));
+        assert_eq!(parse("A"), Some((Key::Char('A'), modifiers(&[]))));
+        assert_eq!(
+            parse("Ctrl+c"),
+            Some((Key::Char('c'), modifiers(&[KeyModifier::Control])))
+        );
+        assert_eq!(
+            parse("ALT+left"),
+            Some((Key::Left, modifiers(&[KeyModifier::Alt])))
+        );
+        assert_eq!(
+            parse("Shift+Tab"),
+            Some((Key::BackTab, modifiers(&[KeyModifier::Shift])))
+        );
+        assert_eq!(
+            parse("BackTab"),
+            Some((Key::BackTab, modifiers(&[KeyModifier::Shift])))
+        );
+        assert_eq!(
+            parse("control+shift+f5"),
+            Some((
+                Key::F(5),
+                modifiers(&[KeyModifier::Control, KeyModifier::Shift])
+            ))
+        );
+        assert_eq!(
+            parse("Keypad7"),
+            Some((Key::Keypad(KeypadKey::Digit(7)), modifiers(&[])))
+        );
+
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("Ctrl+"), None);
+        assert_eq!(parse("Hyper+a"), None);
+        assert_eq!(parse("Enterr"), None);
+        assert_eq!(parse("F"), Some((Key::Char('F'), modifiers(&[]))));
+        assert_eq!(parse("Fx"), None);
+        assert_eq!(parse("Keypad10"), None);
+
+        for string in [
+            "Ctrl+c",
+            "Ctrl+Alt+Shift+F12",
+            "Alt+Plus",
+            "Space",
+            "Shift+BackTab",
+            "Backspace",
+            "Esc",
+            "KeypadDecimal",
+            "Keypad0",
+            "世",
+        ] {
+            assert_eq!(KeyEvent::parse(string).unwrap().to_string(), string);
+        }
+    }
 }
@@ -0,0 +1,261 @@
+//! Drawing images using the graphics protocols of terminals.
+
+use crate::{util::Point, Terminal};
+use std::{collections::HashMap, fmt::Write as _, io::Write};
+
+/// The maximum size of the data in a single escape sequence of the Kitty graphics protocol.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Draws an image with its top left corner at `point` using the Kitty graphics protocol.
+    ///
+    /// `rgba` are the pixels of the image, row by row, with 4 bytes per pixel for red, green, blue and alpha.
+    /// Terminals that don't support the protocol ignore this. The terminal is asked not to reply.
+    pub fn draw_image_kitty(&mut self, point: Point, rgba: &[u8], width: u32, height: u32) {
+        assert_eq!(
+            rgba.len() as u64,
+            width as u64 * height as u64 * 4,
+            "image data doesn't match the size"
+        );
+
+        self.set_cursor(point);
+
+        let data = base64(rgba);
+        let mut chunks = data.as_bytes().chunks(KITTY_CHUNK_SIZE).peekable();
+        let mut control_data = format!("a=T,f=32,s={},v={},q=2,", width, height);
+        while let Some(chunk) = chunks.next() {
+            let more = chunks.peek().is_some() as u8;
+            // Base64 is ASCII so chunks are always valid UTF-8
+            let chunk = std::str::from_utf8(chunk).unwrap();
+            self.write_escape(&format!(
+                "\u{1b}_G{}m={};{}\u{1b}\\",
+                control_data, more, chunk
+            ));
+            // Only the first chunk has the control data other than whether more chunks follow
+            control_data.clear();
+        }
+
+        // The cursor is moved past the image
+        self.cursor = None;
+    }
+
+    /// Draws an image at the cursor using sixel graphics.
+    ///
+    /// `rgba` are the pixels of the image, row by row, with 4 bytes per pixel for red, green, blue and alpha.
+    /// Pixels that are more than half transparent are left out.
+    /// The colors are reduced to at most `max_colors`, which must not be zero.
+    /// Most terminals support at least 256 colors.
+    ///
+    /// Terminals that don't support sixel graphics ignore this.
+    /// See [`device_attributes`](Self::device_attributes) for how to check for support.
+    pub fn draw_image_sixel(&mut self, rgba: &[u8], width: u32, height: u32, max_colors: u16) {
+        assert_eq!(
+            rgba.len() as u64,
+            width as u64 * height as u64 * 4,
+            "image data doesn't match the size"
+        );
+        assert!(max_colors != 0, "there must be at least one color");
+
+        let Quantized { palette, pixels } = quantize(rgba, max_colors as usize);
+        let (width, height) = (width as usize, height as usize);
+
+        // Pixels that are not set are transparent
+        let mut sixel = format!("\u{1b}P0;1q\"1;1;{};{}", width, height);
+        for (index, (r, g, b)) in palette.iter().enumerate() {
+            let percent = |component: u8| (component as u32 * 100 + 127) / 255;
+            write!(
+                sixel,
+                "#{};2;{};{};{}",
+                index,
+                percent(*r),
+                percent(*g),
+                percent(*b)
+            )
+            .unwrap();
+        }
+
+        // Each sixel is a column of 6 pixels
+        for band in (0..height).step_by(6) {
+            if band != 0 {
+                sixel.push('-');
+            }
+            for color in 0..palette.len() {
+                let sixels: Vec<u8> = (0..width)
+                    .map(|x| {
+                        (band..height.min(band + 6))
+                            .filter(|y| pixels[y * width + x] == Some(color))
+                            .fold(0, |sixel, y| sixel | 1 << (y - band))
+                    })
+                    .collect();
+                if sixels.iter().all(|sixel| *sixel == 0) {
+                    continue;
+                }
+
+                write!(sixel, "#{}", color).unwrap();
+                for run in sixels.chunk_by(|a, b| a == b) {
+                    let char = (b'?' + run[0]) as char;
+                    if run.len() > 3 {
+                        write!(sixel, "!{}{}", run.len(), char).unwrap();
+                    } else {
+                        sixel.extend(std::iter::repeat_n(char, run.len()));
+                    }
+                }
+                // Go back to the start of the band for the next color
+                sixel.push('$');
+            }
+        }
+        sixel.push_str("\u{1b}\\");
+
+        self.write_escape(&sixel);
+        self.cursor = None;
+    }
+}
+
+/// An image with a limited amount of colors.
+#[derive(PartialEq, Eq, Debug)]
+struct Quantized {
+    palette: Vec<(u8, u8, u8)>,
+    /// For each pixel, the index of its color in the palette or `None` if it's transparent.
+    pixels: Vec<Option<usize>>,
+}
+
+/// Reduces the colors of the opaque pixels of `rgba` to at most `max_colors` by lowering the precision of the channels.
+fn quantize(rgba: &[u8], max_colors: usize) -> Quantized {
+    'precisions: for dropped_bits in 0..=8 {
+        let mask = (0xff_u16 << dropped_bits) as u8;
+        // The middle of the range of values that are reduced to the same value
+        let middle = ((1_u16 << dropped_bits) - 1) as u8 / 2;
+        let reduce = |component: u8| (component & mask) + middle;
+
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        let mut pixels = Vec::with_capacity(rgba.len() / 4);
+        for pixel in rgba.chunks(4) {
+            if pixel[3] < 128 {
+                pixels.push(None);
+                continue;
+            }
+            let color = (reduce(pixel[0]), reduce(pixel[1]), reduce(pixel[2]));
+            let index = *indices.entry(color).or_insert_with(|| {
+                palette.push(color);
+                palette.len() - 1
+            });
+            if palette.len() > max_colors {
+                continue 'precisions;
+            }
+            pixels.push(Some(index));
+        }
+        return Quantized { palette, pixels };
+    }
+    unreachable!("without any bits there is only one color")
+}
+
+/// Encodes `bytes` in base64 with padding.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut string = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                string.push(ALPHABET[(group >> (18 - 6 * index) & 0b11_1111) as usize] as char);
+            } else {
+                string.push('=');
+            }
+        }
+    }
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+    }
+
+    #[test]
+    fn test_quantize() {
+        let rgba = [255, 0, 0, 255, 254, 0, 0, 255, 0, 0, 255, 255, 0, 0, 0, 0];
+        assert_eq!(
+            quantize(&rgba, 3),
+            Quantized {
+                palette: vec![(255, 0, 0), (254, 0, 0), (0, 0, 255)],
+                pixels: vec![Some(0), Some(1), Some(2), None]
+            }
+        );
+        assert_eq!(
+            quantize(&rgba, 2),
+            Quantized {
+                palette: vec![(254, 0, 0), (0, 0, 254)],
+                pixels: vec![Some(0), Some(0), Some(1), None]
+            }
+        );
+        assert_eq!(quantize(&rgba, 1).palette, [(127, 127, 127)]);
+    }
+
+    #[test]
+    fn test_draw_image_sixel() {
+        let mut terminal = Terminal::test(Size {
+            width: 80,
+            height: 24,
+        });
+
+        terminal.draw_image_sixel(&[255, 0, 0, 255].repeat(4), 2, 2, 256);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}P0;1q\"1;1;2;2#0;2;100;0;0#0BB$\u{1b}\\"
+        );
+
+        // A row of 5 pixels above a transparent row and 6 more rows of another color
+        let mut rgba = [0, 0, 255, 255].repeat(5);
+        rgba.extend([0; 4 * 5]);
+        rgba.extend([0, 255, 0, 255].repeat(5 * 6));
+        terminal.draw_image_sixel(&rgba, 5, 8, 256);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}P0;1q\"1;1;5;8#0;2;0;0;100#1;2;0;100;0#0!5@$#1!5{$-#1!5B$\u{1b}\\"
+        );
+    }
+
+    #[test]
+    fn test_draw_image_kitty() {
+        let mut terminal = Terminal::test(Size {
+            width: 80,
+            height: 24,
+        });
+
+        terminal.draw_image_kitty(
+            Point { x: 1, y: 2 },
+            &[255, 0, 0, 255, 0, 0, 255, 128],
+            2,
+            1,
+        );
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[3;2H\u{1b}_Ga=T,f=32,s=2,v=1,q=2,m=0;/wAA/wAA/4A=\u{1b}\\"
+        );
+
+        // 4096 bytes are 5464 bytes in base64
+        terminal.draw_image_kitty(Point { x: 0, y: 0 }, &[0; 4096], 32, 32);
+        let output = terminal.output();
+        let chunks: Vec<&str> = output.split("\u{1b}\\").collect();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].starts_with("\u{1b}[1;1H\u{1b}_Ga=T,f=32,s=32,v=32,q=2,m=1;AAAA"));
+        assert_eq!(chunks[0].rsplit(';').next().unwrap().len(), 4096);
+        assert!(chunks[1].starts_with("\u{1b}_Gm=0;AAAA"));
+        assert_eq!(chunks[1].rsplit(';').next().unwrap().len(), 5464 - 4096);
+        assert_eq!(chunks[2], "");
+    }
+}
@@ -1,9 +1,32 @@
+mod bidi;
+pub mod buffer;
+mod cursor;
+mod draw;
 pub mod event;
+mod image;
 mod sys;
 pub mod util;
 
-use crate::util::{Point, Size};
-use std::io::{self, Write};
+use crate::{
+    buffer::Buffer,
+    event::{Event, KeyEvent, KeyEventKind},
+    util::{Attributes, Capabilities, Color, ColorDepth, Point, Size},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    ffi::OsString,
+    fmt,
+    io::{self, IsTerminal, Write},
+    marker::PhantomData,
+    ops::{ControlFlow, Deref, DerefMut},
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Whether reverse screen mode is enabled on the standard output stream, so that the panic hook can disable it.
+static REVERSE_SCREEN: AtomicBool = AtomicBool::new(false);
 
 // TODO: add `error` to abort program with message?
 
@@ -11,21 +34,67 @@ use std::io::{self, Write};
 
 // Once https://github.com/rust-lang/rust/pull/78515 is merged, some of this can be changed
 #[derive(Debug)]
-pub struct Terminal<'a> {
-    pub stdout: io::BufWriter<io::StdoutLock<'a>>,
+pub struct Terminal<'a, W: Write = io::StdoutLock<'a>> {
+    pub stdout: io::BufWriter<W>,
     pub size: Size,
     #[cfg(debug_assertions)]
     pub flush_count: usize,
+    bytes_written: usize,
     initialized: bool,
     with_mouse: bool,
+    mouse_pixels: bool,
+    /// Whether reverse screen mode is enabled.
+    reverse_screen: bool,
+    /// Whether the writer is a terminal whose modes, such as raw mode, can be changed.
+    tty: bool,
+    /// Which escape sequences are written. Without escape sequences, only text is written.
+    capabilities: Capabilities,
+    /// Whether colors are written according to the environment variables `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE`.
+    colors_from_env: bool,
+    /// Whether colors are written regardless of the environment variables, if set using `set_color_override`.
+    color_override: Option<bool>,
+    raw_mode: bool,
+    report_key_repeats: bool,
+    report_key_releases: bool,
+    report_unknown_events: bool,
+    decode_ctrl_h: bool,
+    /// Whether the input has ended, so that no more events can be read.
+    input_closed: bool,
+    /// The most recent events read, if recording them is enabled using `enable_event_recording`.
+    recorded_events: VecDeque<Event>,
+    /// How many events are recorded at most, or 0 if recording is disabled.
+    event_recording_capacity: usize,
+    /// The last foreground color set, if known. Used to avoid setting the same color again.
+    foreground_color: Option<Color>,
+    /// The last background color set, if known. Used to avoid setting the same color again.
+    background_color: Option<Color>,
+    /// The attributes currently set.
+    attributes: Attributes,
+    /// The position of the cursor, if known.
+    cursor: Option<Point>,
+    /// Whether the cursor is visible.
+    cursor_visible: bool,
+    /// Whether text wraps at the end of a line, which `measure` needs to know.
+    line_wrap: bool,
+    /// The cursor position saved using `save_cursor_point`, if known.
+    saved_cursor: Option<Point>,
+    /// The palette entries redefined using `set_palette_color`.
+    palette: HashMap<u8, Color>,
+    buffer: Buffer,
     // #[cfg(not(target = "windows"))]
     // stdin: io::Stdin,
+    lifetime: PhantomData<&'a ()>,
 }
 
 #[derive(Clone, Copy, Debug)]
 #[non_exhaustive] // Prevent instantiation
 pub struct NotTTY;
 
+/// The cursor would have left the terminal.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive] // Prevent instantiation
+pub struct OutOfBounds;
+
 /// A terminal with an `io::Stdout` inside.
 ///
 /// Every program can have only a single instance for writing.
@@ -36,34 +105,259 @@ impl<'a> Terminal<'a> {
             return Err(NotTTY);
         }
 
+        Ok(Self::new_or_plain(stdout))
+    }
+
+    /// Creates a terminal even if the standard output stream is not a terminal,
+    /// e.g. because it is piped to a file.
+    ///
+    /// In that case, no escape sequences are written, so that methods changing colors, attributes, the cursor and so on
+    /// do nothing while text is still written as it is.
+    pub fn new_or_plain(stdout: io::StdoutLock<'a>) -> Self {
+        let tty = stdout.is_terminal();
+        let capabilities = capabilities_from_env(|name| env::var_os(name));
         let writer = io::BufWriter::new(stdout);
 
-        Ok(Self {
+        Self {
             stdout: writer,
-            size: Self::size(),
+            size: Self::query_size().unwrap_or_default(),
             #[cfg(debug_assertions)]
             flush_count: 0,
+            bytes_written: 0,
             initialized: false,
-            with_mouse: false
+            with_mouse: false,
+            mouse_pixels: false,
+            reverse_screen: false,
+            tty,
+            capabilities: Capabilities {
+                escapes: tty && capabilities.escapes,
+                ..capabilities
+            },
+            colors_from_env: colors_from_env(|name| env::var_os(name)),
+            color_override: None,
+            raw_mode: false,
+            report_key_repeats: false,
+            report_key_releases: false,
+            report_unknown_events: false,
+            decode_ctrl_h: false,
+            input_closed: false,
+            recorded_events: VecDeque::new(),
+            event_recording_capacity: 0,
+            foreground_color: None,
+            background_color: None,
+            attributes: Attributes::empty(),
+            cursor: None,
+            saved_cursor: None,
+            cursor_visible: true,
+            line_wrap: true,
+            palette: HashMap::new(),
+            buffer: Buffer::default(),
             // #[cfg(not(target = "windows"))]
             // stdin: io::stdin(),
-        })
+            lifetime: PhantomData,
+        }
+    }
+
+    fn set_panic_hook(with_mouse: bool) {
+        let current_panic_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |panic_info| {
+            let stdout = io::stdout();
+
+            let mut terminal = Terminal::new(stdout.lock()).unwrap();
+            terminal.initialized = true;
+            terminal.with_mouse = with_mouse;
+            terminal.reverse_screen = REVERSE_SCREEN.load(Ordering::Relaxed);
+            // The cursor was hidden when the terminal was initialized
+            terminal.cursor_visible = false;
+
+            terminal.deinitialize();
+            terminal.flush(); // Flush so that we can see the following output in the normal view
+
+            current_panic_hook(panic_info);
+        }));
+    }
+}
+
+impl<'a, W: Write> Terminal<'a, W> {
+    /// Queues `string` to be written and returns the amount of bytes queued.
+    pub fn write(&mut self, string: &str) -> usize {
+        self.write_bytes(string.as_bytes())
+    }
+
+    /// Queues `string` followed by a line break to be written and returns the amount of bytes queued.
+    ///
+    /// The line break is CRLF (`\r\n`) because in raw mode, `\n` only moves the cursor down
+    /// without returning it to the start of the line, so that lines written using `write("...\n")` stair-step.
+    pub fn write_line(&mut self, string: &str) -> usize {
+        let count = self.write(string);
+        self.queue_bytes(b"\r\n");
+        if let Some(cursor) = &mut self.cursor {
+            // At the bottom, the screen scrolls up instead
+            *cursor = Point {
+                x: 0,
+                y: (cursor.y + 1).min(self.size.height.saturating_sub(1)),
+            };
+        }
+        count + 2
     }
 
-    pub fn write(&mut self, string: &str) {
-        self.stdout.write_all(string.as_bytes()).unwrap();
+    /// Queues `string` to be written in the colors `fg` and `bg` and returns the amount of bytes of text queued.
+    ///
+    /// Only the colors that aren't already set are set, and afterwards the colors that were set before are restored,
+    /// or reset to the terminal's defaults if they weren't known.
+    pub fn write_colored(&mut self, string: &str, fg: Color, bg: Color) -> usize {
+        let (previous_fg, previous_bg) = (self.foreground_color, self.background_color);
+        self.set_foreground_color(fg);
+        self.set_background_color(bg);
+        let count = self.write(string);
+        match previous_fg {
+            Some(color) => self.set_foreground_color(color),
+            None if self.foreground_color.is_some() => self.reset_foreground_color_only(),
+            None => {}
+        }
+        match previous_bg {
+            Some(color) => self.set_background_color(color),
+            None if self.background_color.is_some() => self.reset_background_color_only(),
+            None => {}
+        }
+        count
     }
 
-    pub fn write_bytes(&mut self, bytes: &[u8]) {
+    /// Queues `bytes` to be written and returns the amount of bytes queued.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> usize {
+        self.queue_bytes(bytes);
+        self.track_text(bytes);
+        bytes.len()
+    }
+
+    fn queue_bytes(&mut self, bytes: &[u8]) {
         self.stdout.write_all(bytes).unwrap();
+        self.bytes_written += bytes.len();
+    }
+
+    /// Queues the escape sequence `escape` to be written, unless escape sequences are suppressed.
+    pub(crate) fn write_escape(&mut self, escape: &str) {
+        if self.capabilities.escapes {
+            self.queue_bytes(escape.as_bytes());
+        }
+    }
+
+    /// Like [`write_escape`](Self::write_escape), but formats `escape` directly into the output
+    /// so that no string has to be allocated for it.
+    pub(crate) fn write_escape_fmt(&mut self, escape: fmt::Arguments) {
+        /// Queues what is written to it.
+        struct Queue<'t, 'a, W: Write>(&'t mut Terminal<'a, W>);
+
+        impl<W: Write> Write for Queue<'_, '_, W> {
+            fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+                self.0.queue_bytes(bytes);
+                Ok(bytes.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        if self.capabilities.escapes {
+            Queue(self).write_fmt(escape).unwrap();
+        }
+    }
+
+    /// Returns whether this terminal writes to an actual terminal rather than, for example, a file or a pipe.
+    pub fn is_terminal(&self) -> bool {
+        self.tty
+    }
+
+    /// Sets whether escape sequences are suppressed, in which case methods changing colors, attributes, the cursor
+    /// and so on do nothing while text is still written as it is.
+    ///
+    /// By default, escape sequences are only suppressed if the terminal was created using
+    /// [`new_or_plain`](Terminal::new_or_plain) and the output is not a terminal, or if `TERM` is `dumb`.
+    pub fn set_escape_suppression(&mut self, suppress_escapes: bool) {
+        if self.capabilities.escapes == suppress_escapes {
+            // The cursor was or is no longer moved as kept track of
+            self.cursor = None;
+            self.saved_cursor = None;
+        }
+        self.capabilities.escapes = !suppress_escapes;
+    }
+
+    /// Returns which escape sequences are written, which all methods writing escape sequences adhere to.
+    ///
+    /// By default, this is detected using the environment variables `TERM` and `COLORTERM` and
+    /// whether the output is a terminal.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Sets which escape sequences are written regardless of what was detected, e.g. to test how a program
+    /// behaves on a terminal that doesn't support them.
+    ///
+    /// This is the same as using [`set_escape_suppression`](Self::set_escape_suppression) and
+    /// [`set_color_depth`](Self::set_color_depth) besides setting whether REP is used.
+    pub fn force_capabilities(&mut self, capabilities: Capabilities) {
+        self.set_escape_suppression(!capabilities.escapes);
+        self.set_color_depth(capabilities.color_depth);
+        self.capabilities.rep = capabilities.rep;
+    }
+
+    /// Sets whether colors are written regardless of the environment, or, if `None`, according to it.
+    ///
+    /// By default, colors are not written if the environment variable `NO_COLOR` is set to anything but an empty string
+    /// or `CLICOLOR` is set to `0`, unless `CLICOLOR_FORCE` is set to anything but an empty string or `0`.
+    /// They are also not written if the [color depth](Self::set_color_depth) is [`ColorDepth::Monochrome`].
+    /// Without colors, methods setting colors, such as [`set_foreground_color`](Self::set_foreground_color),
+    /// do nothing while text and other escape sequences are still written.
+    pub fn set_color_override(&mut self, colors: Option<bool>) {
+        self.color_override = colors;
+    }
+
+    /// Returns whether colors are written. See [`set_color_override`](Self::set_color_override).
+    ///
+    /// Colors are never written while escape sequences are suppressed.
+    pub fn colors_enabled(&self) -> bool {
+        self.capabilities.escapes
+            && self.color_override.unwrap_or(
+                self.colors_from_env && self.capabilities.color_depth != ColorDepth::Monochrome,
+            )
+    }
+
+    /// Sets the colors the terminal can display. Colors that are set are reduced to the closest ones it can display,
+    /// e.g. RGB colors to 8-bit colors for [`ColorDepth::Ansi256`].
+    ///
+    /// By default, the depth is detected using the environment variables `COLORTERM` and `TERM`.
+    pub fn set_color_depth(&mut self, depth: ColorDepth) {
+        self.capabilities.color_depth = depth;
+    }
+
+    /// Returns the colors the terminal can display. See [`set_color_depth`](Self::set_color_depth).
+    pub fn color_depth(&self) -> ColorDepth {
+        self.capabilities.color_depth
+    }
+
+    /// Returns the amount of bytes queued since the last [`flush`](Self::flush).
+    pub fn bytes_written_since_flush(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// Returns the size of the terminal.
+    ///
+    /// This does not query the terminal but returns the size that is kept up to date by [`read_event`](Self::read_event).
+    pub fn size(&self) -> Size {
+        self.size
     }
 
+    /// Renders the cells set using [`set_cell`](Self::set_cell) and writes everything to the terminal.
     pub fn flush(&mut self) {
+        self.render_buffer();
         self.stdout.flush().unwrap();
+        self.bytes_written = 0;
 
         #[cfg(debug_assertions)]
         {
-            if self.initialized {
+            if self.initialized && self.capabilities.escapes {
                 self.flush_count += 1;
                 self.save_cursor_point();
                 self.set_cursor(Point { x: 0, y: 0 });
@@ -74,25 +368,6 @@ impl<'a> Terminal<'a> {
         }
     }
 
-    fn set_panic_hook(with_mouse: bool) {
-        use std::panic;
-
-        let current_panic_hook = panic::take_hook();
-
-        panic::set_hook(Box::new(move |panic_info| {
-            let stdout = io::stdout();
-
-            let mut terminal = Terminal::new(stdout.lock()).unwrap();
-            terminal.initialized = true;
-            terminal.with_mouse = with_mouse;
-
-            terminal.deinitialize();
-            terminal.flush(); // Flush so that we can see the following output in the normal view
-
-            current_panic_hook(panic_info);
-        }));
-    }
-
     /// Makes this terminal suitable for drawing and input.
     ///
     /// Note that this does not do anything until [`flush`] is used.
@@ -109,7 +384,10 @@ impl<'a> Terminal<'a> {
             self.enable_mouse_capture();
         }
 
-        Self::set_panic_hook(with_mouse);
+        // The panic hook restores the standard output stream, so it's only of use if that's what is written to
+        if self.tty {
+            Terminal::<io::StdoutLock>::set_panic_hook(with_mouse);
+        }
 
         self.initialized = true;
     }
@@ -129,19 +407,1013 @@ impl<'a> Terminal<'a> {
         if self.with_mouse {
             self.disable_mouse_capture();
         }
+        self.disable_reverse_screen();
 
         self.initialized = false;
     }
 
+    /// Sets whether keys being held down are reported as [`KeyEventKind::Repeat`] events. By default they are not.
+    pub fn set_report_key_repeats(&mut self, report: bool) {
+        self.report_key_repeats = report;
+    }
+
+    /// Sets whether key releases are reported as [`KeyEventKind::Release`] events. By default they are not.
+    pub fn set_report_key_releases(&mut self, report: bool) {
+        self.report_key_releases = report;
+    }
+
+    /// Sets whether input that doesn't map to any other event is reported as [`Event::Unknown`]
+    /// rather than being discarded. By default it is not.
+    pub fn set_report_unknown_events(&mut self, report: bool) {
+        self.report_unknown_events = report;
+    }
+
+    /// Sets whether the control character 0x08 is reported as Ctrl+H rather than [`Key::Backspace`](event::Key::Backspace).
+    /// By default it is Backspace, which is what many terminals send for the Backspace key, while others send 0x7f.
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    pub fn set_decode_ctrl_h(&mut self, decode: bool) {
+        self.decode_ctrl_h = decode;
+    }
+
+    /// Starts keeping the `capacity` most recent events read, which can be retrieved using
+    /// [`recorded_events`](Self::recorded_events), e.g. to include them in bug reports.
+    ///
+    /// Only the events that are reported according to the settings are recorded. A `capacity` of 0 disables recording.
+    pub fn enable_event_recording(&mut self, capacity: usize) {
+        self.event_recording_capacity = capacity;
+        let excess = self.recorded_events.len().saturating_sub(capacity);
+        self.recorded_events.drain(..excess);
+        self.recorded_events.shrink_to(capacity);
+        self.recorded_events.make_contiguous();
+    }
+
+    /// Returns the events recorded since recording was enabled using
+    /// [`enable_event_recording`](Self::enable_event_recording), from the oldest to the most recent.
+    pub fn recorded_events(&self) -> &[Event] {
+        // `record_event` keeps the events contiguous
+        self.recorded_events.as_slices().0
+    }
+
+    /// Records `event` if recording is enabled and returns it.
+    pub(crate) fn record_event(&mut self, event: Option<Event>) -> Option<Event> {
+        if let (Some(event), 1..) = (&event, self.event_recording_capacity) {
+            if self.recorded_events.len() == self.event_recording_capacity {
+                self.recorded_events.pop_front();
+            }
+            self.recorded_events.push_back(event.clone());
+            // Rotates the events only once the buffer wraps around so that they can be returned as a slice
+            self.recorded_events.make_contiguous();
+        }
+        event
+    }
+
+    /// Reads events until `deadline` has passed or `max` events have been read, whichever comes first.
+    ///
+    /// This is useful for handling all the input that arrives within the time budget of a frame at once.
+    /// If `deadline` has already passed, only the events that are available right away are read.
+    /// Reading stops early if the input has been closed; see [`input_closed`](Self::input_closed).
+    pub fn read_events_until(&mut self, deadline: Instant, max: usize) -> Vec<Event> {
+        collect_events(deadline, max, |timeout| match self.poll_event(timeout) {
+            None if self.input_closed => ControlFlow::Break(()),
+            event => ControlFlow::Continue(event),
+        })
+    }
+
+    /// Returns whether the input has been closed, e.g. because the standard input stream has reached its end,
+    /// in which case no more events are read.
+    pub fn input_closed(&self) -> bool {
+        self.input_closed
+    }
+
+    /// Passes each event read to `handler` until it returns [`ControlFlow::Break`] or the input is closed.
+    ///
+    /// While waiting for events, this blocks instead of polling repeatedly, so an idle program doesn't use the CPU.
+    /// Like with [`read_event`](Self::read_event), the size is already updated when `handler` gets a resize.
+    pub fn run(&mut self, handler: impl FnMut(&mut Self, Event) -> ControlFlow<()>) {
+        self.run_with(Self::read_event, handler);
+    }
+
+    /// Like [`run`](Self::run), but reads the events using `read`.
+    fn run_with(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Option<Event>,
+        mut handler: impl FnMut(&mut Self, Event) -> ControlFlow<()>,
+    ) {
+        loop {
+            let flow = match read(self) {
+                Some(event) => handler(self, event),
+                None if self.input_closed => ControlFlow::Break(()),
+                None => ControlFlow::Continue(()),
+            };
+            if flow.is_break() {
+                return;
+            }
+        }
+    }
+
+    /// Returns whether `event` should be reported or discarded according to the settings.
+    fn is_reported(&self, event: &Event) -> bool {
+        match event {
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Repeat,
+                ..
+            }) => self.report_key_repeats,
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            }) => self.report_key_releases,
+            Event::Unknown(_) => self.report_unknown_events,
+            _ => true,
+        }
+    }
+
+    /// Runs `f` with raw mode enabled and disables it afterwards, even if `f` panics.
+    pub fn with_raw_mode<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.enable_raw_mode();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.disable_raw_mode();
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Shows or hides the cursor. Does nothing if it's already visible or hidden, respectively.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if visible {
+            self.show_cursor();
+        } else {
+            self.hide_cursor();
+        }
+    }
+
+    /// Returns whether the cursor is visible.
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Runs `f` with the cursor hidden and makes it visible again afterwards if it was before, even if `f` panics.
+    pub fn with_cursor_hidden<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let visible = self.cursor_visible;
+        self.hide_cursor();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.set_cursor_visible(visible);
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Runs `f` in the alternate screen and leaves it afterwards, even if `f` panics.
+    ///
+    /// Note that this does not do anything until [`flush`] is used.
+    pub fn with_alternate_screen<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.enter_alternate_dimension();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.exit_alternate_dimension();
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Enables mouse capture until the returned guard is dropped.
+    ///
+    /// If mouse capture was already enabled, e.g. by an outer widget, it stays enabled after the guard is dropped.
+    /// The terminal can be used through the guard in the meantime.
+    pub fn capture_mouse(&mut self) -> MouseCaptureGuard<'_, 'a, W> {
+        let was_enabled = self.with_mouse;
+        self.enable_mouse_capture();
+        MouseCaptureGuard {
+            terminal: self,
+            was_enabled,
+        }
+    }
+
+    /// Runs `f` to draw a frame in a synchronized update and flushes afterwards, even if `f` panics,
+    /// so that the frame is displayed at once.
+    ///
+    /// This also renders the cells set using [`set_cell`](Self::set_cell) within the update.
+    /// Terminals that don't support synchronized updates display the frame as it is written.
+    pub fn frame<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.begin_synchronized_update();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.render_buffer();
+        self.end_synchronized_update();
+        self.flush();
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Writes `text` with its foreground color fading from `start` to `end`.
+    ///
+    /// The colors are interpolated in RGB by the display width of the text
+    /// so that wide characters don't skew the gradient.
+    pub fn print_gradient(&mut self, text: &str, start: Color, end: Color) {
+        use unicode_width::UnicodeWidthChar;
+
+        let start = start.to_rgb();
+        let end = end.to_rgb();
+
+        // The column of the last character is where the gradient ends
+        let mut width = 0;
+        let mut last_column = 0;
+        for char_width in text.chars().filter_map(|char| char.width()) {
+            if char_width != 0 {
+                last_column = width;
+                width += char_width;
+            }
+        }
+
+        let interpolate = |start: u8, end: u8, column: usize| -> u8 {
+            (start as usize * (last_column - column) + end as usize * column + last_column / 2)
+                .checked_div(last_column)
+                .map_or(start, |component| component as u8)
+        };
+
+        let mut column = 0;
+        let mut buffer = [0; 4];
+        for char in text.chars() {
+            let width = char.width().unwrap_or(0);
+            if width != 0 {
+                self.set_foreground_color(Color::Rgb {
+                    r: interpolate(start.0, end.0, column),
+                    g: interpolate(start.1, end.1, column),
+                    b: interpolate(start.2, end.2, column),
+                });
+                column += width;
+            }
+            self.write(char.encode_utf8(&mut buffer));
+        }
+    }
+
     pub fn contains(&self, point: Point) -> bool {
         point.x > 0 && point.x < self.size.width && point.y < self.size.height && point.y > 0
     }
 }
 
+/// Returns whether colors should be written according to the environment variables returned by `var`.
+///
+/// See <https://no-color.org> and <https://bixense.com/clicolors>.
+fn colors_from_env(var: impl Fn(&str) -> Option<OsString>) -> bool {
+    let forced = var("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0");
+    let no_color = var("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let clicolor_off = var("CLICOLOR").is_some_and(|value| value == "0");
+    forced || !(no_color || clicolor_off)
+}
+
+/// Returns which escape sequences the terminal understands according to the environment variables returned by `var`.
+fn capabilities_from_env(var: impl Fn(&str) -> Option<OsString>) -> Capabilities {
+    Capabilities {
+        // Dumb terminals, such as the output pane of some editors, only understand text
+        escapes: var("TERM").is_none_or(|term| term != "dumb"),
+        color_depth: color_depth_from_env(&var),
+        // Many terminals claim to be xterm without understanding REP, so only trust the ones that don't
+        rep: var("TERM").is_some_and(|term| {
+            let term = term.to_string_lossy();
+            term.starts_with("foot") || term == "xterm-kitty"
+        }),
+    }
+}
+
+/// Returns the colors the terminal can display according to the environment variables returned by `var`.
+fn color_depth_from_env(var: impl Fn(&str) -> Option<OsString>) -> ColorDepth {
+    if var("COLORTERM").is_some_and(|value| value == "truecolor" || value == "24bit") {
+        return ColorDepth::TrueColor;
+    }
+    match var("TERM") {
+        // The Windows console doesn't set `TERM` and supports RGB colors
+        None if cfg!(windows) => ColorDepth::TrueColor,
+        None => ColorDepth::Ansi16,
+        Some(term) => {
+            let term = term.to_string_lossy();
+            if term == "dumb" {
+                ColorDepth::Monochrome
+            } else if term.ends_with("-direct") || term.contains("truecolor") {
+                ColorDepth::TrueColor
+            } else if term.contains("256color") {
+                ColorDepth::Ansi256
+            } else {
+                ColorDepth::Ansi16
+            }
+        }
+    }
+}
+
+/// Collects the events returned by `poll` until `deadline` has passed or there are `max` events.
+///
+/// `poll` is given the time remaining and returns `None` if no event was available in time,
+/// or [`ControlFlow::Break`] if no more events can be read.
+fn collect_events(
+    deadline: Instant,
+    max: usize,
+    mut poll: impl FnMut(Duration) -> ControlFlow<(), Option<Event>>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    while events.len() < max {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match poll(timeout) {
+            ControlFlow::Continue(Some(event)) => events.push(event),
+            ControlFlow::Continue(None) if timeout.is_zero() => break,
+            // An event that is not reported might have been read before the deadline
+            ControlFlow::Continue(None) => {}
+            ControlFlow::Break(()) => break,
+        }
+    }
+    events
+}
+
+/// Keeps mouse capture enabled while it lives. Returned by [`Terminal::capture_mouse`].
+#[must_use = "mouse capture is disabled again when the guard is dropped"]
+pub struct MouseCaptureGuard<'t, 'a, W: Write> {
+    terminal: &'t mut Terminal<'a, W>,
+    was_enabled: bool,
+}
+
+impl<'a, W: Write> Deref for MouseCaptureGuard<'_, 'a, W> {
+    type Target = Terminal<'a, W>;
+
+    fn deref(&self) -> &Self::Target {
+        self.terminal
+    }
+}
+
+impl<W: Write> DerefMut for MouseCaptureGuard<'_, '_, W> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.terminal
+    }
+}
+
+impl<W: Write> Drop for MouseCaptureGuard<'_, '_, W> {
+    fn drop(&mut self) {
+        if !self.was_enabled {
+            self.terminal.disable_mouse_capture();
+        }
+    }
+}
+
+/// Writing to the terminal directly, for example to queue crossterm commands or to use [`write!`].
+///
+/// Note that, unlike [`Terminal::flush`], [`Write::flush`] does not render the cells set using [`Terminal::set_cell`].
+impl<'a, W: Write> Write for Terminal<'a, W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let count = self.stdout.write(bytes)?;
+        self.bytes_written += count;
+        // What is written is unknown
+        self.cursor = None;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        self.bytes_written = 0;
+        Ok(())
+    }
+}
+
+/// Flushes what is queued so that it isn't lost, ignoring errors.
+///
+/// Flushing explicitly using [`flush`](Terminal::flush) is still recommended so that output becomes visible
+/// when intended rather than when the terminal is dropped. This does not render the cells set using
+/// [`set_cell`](Terminal::set_cell).
+impl<'a, W: Write> Drop for Terminal<'a, W> {
+    fn drop(&mut self) {
+        let _ = self.stdout.flush();
+    }
+}
+
+/// A terminal writing into any writer, such as a pseudoterminal, a socket or a file.
+impl<W: Write> Terminal<'static, W> {
+    /// Creates a terminal writing into `writer` and assumed to be of `size`.
+    ///
+    /// This is not named `new` because [`new`](Terminal::new) already creates a terminal writing into
+    /// the standard output stream, and renaming that would break existing code.
+    ///
+    /// Because `writer` is not known to be a terminal, raw mode is never entered and, by default,
+    /// escape sequences and colors are written regardless of `TERM` and `NO_COLOR`; see
+    /// [`force_capabilities`](Self::force_capabilities). [`initialize`](Self::initialize) doesn't install a panic hook
+    /// and [`deinitialize`](Self::deinitialize) writes into `writer` like all other methods.
+    /// Events are still read from the standard input stream, and when they include a resize,
+    /// [`size`](Self::size) becomes the size of the standard output stream, so set it again if it's different.
+    pub fn with_writer(writer: W, size: Size) -> Self {
+        Self {
+            stdout: io::BufWriter::new(writer),
+            size,
+            #[cfg(debug_assertions)]
+            flush_count: 0,
+            bytes_written: 0,
+            initialized: false,
+            with_mouse: false,
+            mouse_pixels: false,
+            reverse_screen: false,
+            tty: false,
+            capabilities: Capabilities {
+                escapes: true,
+                color_depth: ColorDepth::TrueColor,
+                rep: false,
+            },
+            colors_from_env: true,
+            color_override: None,
+            raw_mode: false,
+            report_key_repeats: false,
+            report_key_releases: false,
+            report_unknown_events: false,
+            decode_ctrl_h: false,
+            input_closed: false,
+            recorded_events: VecDeque::new(),
+            event_recording_capacity: 0,
+            foreground_color: None,
+            background_color: None,
+            attributes: Attributes::empty(),
+            cursor: None,
+            saved_cursor: None,
+            cursor_visible: true,
+            line_wrap: true,
+            palette: HashMap::new(),
+            buffer: Buffer::default(),
+            lifetime: PhantomData,
+        }
+    }
+}
+
+/// A terminal writing into memory so that tests can inspect what would have been written.
+#[cfg(test)]
+impl Terminal<'static, Vec<u8>> {
+    pub(crate) fn test(size: Size) -> Self {
+        Self::with_writer(Vec::new(), size)
+    }
+
+    /// Returns and clears everything written so far.
+    pub(crate) fn output(&mut self) -> String {
+        self.stdout.flush().unwrap();
+        String::from_utf8(std::mem::take(self.stdout.get_mut())).unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use std::{
+        cell::{Cell, RefCell},
+        collections::VecDeque,
+        rc::Rc,
+    };
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    fn terminal() -> Terminal<'static, Vec<u8>> {
+        Terminal::test(Size {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    #[test]
+    fn test_bytes_written() {
+        let mut terminal = terminal();
+        assert_eq!(terminal.write("äb"), 3);
+        assert_eq!(terminal.write_bytes(b"cd"), 2);
+        terminal.set_foreground_color(Color::Byte(100));
+        assert_eq!(
+            terminal.bytes_written_since_flush(),
+            5 + "\u{1b}[38;5;100m".len()
+        );
+        terminal.flush();
+        assert_eq!(terminal.bytes_written_since_flush(), 0);
+    }
+
+    #[test]
+    fn test_write_line() {
+        let mut terminal = terminal();
+        terminal.set_cursor(Point { x: 5, y: 3 });
+        terminal.output();
+
+        assert_eq!(terminal.write_line("text"), 6);
+        assert_eq!(terminal.output(), "text\r\n");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 4 }));
+
+        terminal.set_cursor(Point { x: 0, y: 23 });
+        terminal.write_line("");
+        assert_eq!(terminal.cursor_point(), Some(Point { x: 0, y: 23 }));
+    }
+
+    #[test]
+    fn test_write_colored() {
+        let mut terminal = terminal();
+        terminal.set_foreground_color(Color::Red);
+        terminal.output();
+        // What the backend writes for a color
+        let foreground = |color| {
+            let mut terminal = self::terminal();
+            terminal.set_foreground_color(color);
+            terminal.output()
+        };
+        let background = |color| {
+            let mut terminal = self::terminal();
+            terminal.set_background_color(color);
+            terminal.output()
+        };
+
+        // Only the background differs and it's reset afterwards because it wasn't known before
+        assert_eq!(terminal.write_colored("text", Color::Red, Color::Blue), 4);
+        assert_eq!(
+            terminal.output(),
+            format!("{}text\x1b[49m", background(Color::Blue))
+        );
+        assert_eq!(terminal.foreground_color, Some(Color::Red));
+        assert_eq!(terminal.background_color, None);
+
+        // The previous foreground is restored rather than reset
+        terminal.write_colored("text", Color::Green, Color::Blue);
+        assert_eq!(
+            terminal.output(),
+            format!(
+                "{}{}text{}\x1b[49m",
+                foreground(Color::Green),
+                background(Color::Blue),
+                foreground(Color::Red)
+            )
+        );
+        assert_eq!(terminal.foreground_color, Some(Color::Red));
+    }
+
+    #[test]
+    fn test_escape_suppression() {
+        let mut terminal = terminal();
+        assert!(!terminal.is_terminal());
+        terminal.set_escape_suppression(true);
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_background_color(Color::Blue);
+        terminal.set_cursor(Point { x: 1, y: 1 });
+        terminal.enable_italic();
+        terminal.write("text");
+        terminal.reset_colors();
+        terminal.flush();
+        assert_eq!(terminal.output(), "text");
+
+        terminal.set_escape_suppression(false);
+        terminal.set_foreground_color(Color::Red);
+        terminal.write("text");
+        assert_eq!(terminal.output(), "\u{1b}[38;5;9mtext");
+    }
+
+    #[test]
+    fn test_with_raw_mode() {
+        let mut terminal = terminal();
+
+        assert!(terminal.with_raw_mode(|terminal| terminal.raw_mode));
+        assert!(!terminal.raw_mode);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            terminal.with_raw_mode(|_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert!(!terminal.raw_mode);
+    }
+
+    #[test]
+    fn test_with_alternate_screen() {
+        let mut terminal = terminal();
+
+        terminal.with_alternate_screen(|terminal| terminal.write("a"));
+        assert_eq!(terminal.output(), "\u{1b}[?1049ha\u{1b}[?1049l");
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            terminal.with_alternate_screen(|_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(terminal.output(), "\u{1b}[?1049h\u{1b}[?1049l");
+    }
+
+    #[test]
+    fn test_cursor_visibility() {
+        let mut terminal = terminal();
+
+        terminal.hide_cursor();
+        let hide = terminal.output();
+        assert!(!hide.is_empty());
+        terminal.hide_cursor();
+        terminal.set_cursor_visible(false);
+        assert_eq!(terminal.output(), "");
+        assert!(!terminal.is_cursor_visible());
+
+        // The cursor stays hidden if it was hidden before
+        terminal.with_cursor_hidden(|terminal| terminal.write("a"));
+        assert_eq!(terminal.output(), "a");
+        assert!(!terminal.is_cursor_visible());
+
+        terminal.show_cursor();
+        let show = terminal.output();
+        assert!(!show.is_empty());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            terminal.with_cursor_hidden(|_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(terminal.output(), hide + &show);
+        assert!(terminal.is_cursor_visible());
+    }
+
+    #[test]
+    fn test_mouse_capture() {
+        let mut terminal = terminal();
+
+        terminal.enable_mouse_capture();
+        let enable = terminal.output();
+        assert!(!enable.is_empty());
+        terminal.enable_mouse_capture();
+        assert_eq!(terminal.output(), "");
+        terminal.disable_mouse_capture();
+        let disable = terminal.output();
+        assert!(!disable.is_empty());
+        terminal.disable_mouse_capture();
+        assert_eq!(terminal.output(), "");
+
+        let mut guard = terminal.capture_mouse();
+        assert_eq!(guard.output(), enable);
+        guard.enable_mouse_capture();
+        assert_eq!(guard.output(), "");
+        drop(guard);
+        assert_eq!(terminal.output(), disable);
+        assert!(!terminal.with_mouse);
+
+        // A nested guard leaves mouse capture enabled
+        terminal.enable_mouse_capture();
+        terminal.output();
+        drop(terminal.capture_mouse());
+        assert_eq!(terminal.output(), "");
+        assert!(terminal.with_mouse);
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut terminal = terminal();
+
+        terminal.frame(|terminal| {
+            terminal.write("a");
+            terminal.set_cell(Point { x: 1, y: 0 }, buffer::Cell::default());
+        });
+        assert_eq!(terminal.bytes_written_since_flush(), 0);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[?2026ha\u{1b}[1;2H\u{1b}[38;5;7m\u{1b}[48;5;0m \u{1b}[?2026l"
+        );
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            terminal.frame(|_| panic!("closure panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(terminal.output(), "\u{1b}[?2026h\u{1b}[?2026l");
+    }
+
+    #[test]
+    fn test_collect_events() {
+        let script = || {
+            let event = |char| Event::Key(KeyEvent::from(event::Key::Char(char)));
+            VecDeque::from([Some(event('a')), None, Some(event('b')), Some(event('c'))])
+        };
+
+        // The deadline has passed, so only what's available right away is read
+        let mut events = script();
+        let deadline = Instant::now() - Duration::from_millis(1);
+        let collected = collect_events(deadline, 10, |timeout| {
+            assert_eq!(timeout, Duration::ZERO);
+            ControlFlow::Continue(events.pop_front().flatten())
+        });
+        assert_eq!(collected.len(), 1);
+        assert_eq!(events.len(), 2);
+
+        // Events that are not reported don't end the reading before the deadline
+        let mut events = script();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let collected = collect_events(deadline, 3, |timeout| {
+            assert!(timeout > Duration::ZERO);
+            ControlFlow::Continue(events.pop_front().flatten())
+        });
+        assert_eq!(collected.len(), 3);
+        assert!(events.is_empty());
+
+        // Reading stops once the input is closed, even before the deadline
+        let mut events = script();
+        let collected = collect_events(deadline, 10, |_| match events.pop_front() {
+            Some(event) => ControlFlow::Continue(event),
+            None => ControlFlow::Break(()),
+        });
+        assert_eq!(collected.len(), 3);
+    }
+
+    #[test]
+    fn test_with_writer() {
+        let mut terminal = Terminal::with_writer(
+            Vec::new(),
+            Size {
+                width: 10,
+                height: 2,
+            },
+        );
+        terminal.cursor_home();
+        terminal.write_line("first");
+        terminal.write("second");
+        terminal.flush();
+        assert_eq!(terminal.stdout.get_ref(), b"\x1b[Hfirst\r\nsecond");
+        assert_eq!(terminal.cursor, Some(Point { x: 6, y: 1 }));
+    }
+
+    #[test]
+    fn test_with_writer_cleanup() {
+        let mut terminal = terminal();
+        terminal.initialize(None, true);
+        terminal.output();
+        terminal.deinitialize();
+        // Everything goes into the writer rather than the standard output stream
+        let output = terminal.output();
+        assert!(output.contains("\x1b[?1049l"));
+        assert!(output.contains("\x1b[?1000l"));
+    }
+
+    #[test]
+    fn test_flush_on_drop() {
+        /// Shares what is written with the test.
+        #[derive(Default)]
+        struct Capture {
+            bytes: Rc<RefCell<Vec<u8>>>,
+            flushed: Rc<Cell<bool>>,
+        }
+
+        impl Write for Capture {
+            fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+                self.bytes.borrow_mut().extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.flushed.set(true);
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let (bytes, flushed) = (capture.bytes.clone(), capture.flushed.clone());
+        let mut terminal = Terminal::with_writer(
+            capture,
+            Size {
+                width: 80,
+                height: 24,
+            },
+        );
+        terminal.set_foreground_color(Color::Red);
+        terminal.write("text");
+        assert!(bytes.borrow().is_empty());
+
+        drop(terminal);
+        assert_eq!(*bytes.borrow(), b"\x1b[38;5;9mtext");
+        assert!(flushed.get());
+    }
+
+    #[test]
+    fn test_run() {
+        let mut terminal = terminal();
+        let key = |char| Some(Event::Key(KeyEvent::from(event::Key::Char(char))));
+        let resize = Event::Resize {
+            old: terminal.size(),
+            new: Size {
+                width: 100,
+                height: 50,
+            },
+        };
+        let mut script = VecDeque::from([key('a'), None, Some(resize), key('q'), key('b')]);
+
+        let mut handled = Vec::new();
+        terminal.run_with(
+            |terminal| {
+                let event = script.pop_front().unwrap();
+                if let Some(Event::Resize { new, .. }) = event {
+                    terminal.size = new;
+                }
+                event
+            },
+            |terminal, event| {
+                if let Event::Resize { new, .. } = event {
+                    assert_eq!(terminal.size(), new);
+                }
+                handled.push(event.clone());
+                if event == key('q').unwrap() {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert_eq!(handled.len(), 3);
+        assert_eq!(script, [key('b')]);
+
+        // Running ends once the input is closed
+        let mut script = VecDeque::from([key('a'), None, key('b')]);
+        let mut handled = 0;
+        terminal.run_with(
+            |terminal| {
+                let event = script.pop_front().flatten();
+                terminal.input_closed = script.is_empty();
+                event
+            },
+            |_, _| {
+                handled += 1;
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(handled, 2);
+        assert!(terminal.input_closed());
+    }
+
+    #[test]
+    fn test_event_recording() {
+        let mut terminal = terminal();
+        let key = |char| Event::Key(KeyEvent::from(event::Key::Char(char)));
+
+        // Nothing is recorded by default
+        assert_eq!(terminal.record_event(Some(key('a'))), Some(key('a')));
+        assert_eq!(terminal.recorded_events(), []);
+
+        terminal.enable_event_recording(3);
+        for char in ['a', 'b', 'c', 'd', 'e'] {
+            terminal.record_event(Some(key(char)));
+            terminal.record_event(None);
+        }
+        assert_eq!(terminal.recorded_events(), [key('c'), key('d'), key('e')]);
+
+        terminal.enable_event_recording(2);
+        assert_eq!(terminal.recorded_events(), [key('d'), key('e')]);
+        terminal.enable_event_recording(0);
+        terminal.record_event(Some(key('f')));
+        assert_eq!(terminal.recorded_events(), []);
+    }
+
+    #[test]
+    fn test_rep_from_env() {
+        fn rep(term: &str) -> bool {
+            capabilities_from_env(|name| (name == "TERM").then(|| OsString::from(term))).rep
+        }
+        assert!(rep("foot"));
+        assert!(rep("foot-direct"));
+        assert!(rep("xterm-kitty"));
+        assert!(!rep("xterm-256color"));
+        assert!(!rep("dumb"));
+        assert!(!capabilities_from_env(|_| None).rep);
+    }
+
+    #[test]
+    fn test_dumb_terminal() {
+        let capabilities =
+            capabilities_from_env(|name| (name == "TERM").then(|| OsString::from("dumb")));
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                escapes: false,
+                color_depth: ColorDepth::Monochrome,
+                rep: false,
+            }
+        );
+
+        let mut terminal = terminal();
+        terminal.force_capabilities(capabilities);
+        terminal.clear();
+        terminal.set_cursor(Point { x: 1, y: 1 });
+        terminal.set_foreground_color(Color::Red);
+        terminal.enable_italic();
+        terminal.write_line("text");
+        terminal.write("more");
+        assert_eq!(terminal.output(), "text\r\nmore");
+        assert_eq!(terminal.capabilities(), capabilities);
+    }
+
+    #[test]
+    fn test_state_while_escapes_suppressed() {
+        let mut terminal = terminal();
+        terminal.set_cursor(Point { x: 1, y: 1 });
+        terminal.output();
+        terminal.set_escape_suppression(true);
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_attributes(Attributes::from(&[util::Attribute::Bold][..]));
+        terminal.hide_cursor();
+        terminal.set_cursor(Point { x: 2, y: 2 });
+        assert_eq!(terminal.output(), "");
+
+        // What was left out is written once escape sequences are no longer suppressed
+        terminal.set_escape_suppression(false);
+        assert_eq!(terminal.cursor_point(), None);
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_attributes(Attributes::from(&[util::Attribute::Bold][..]));
+        terminal.hide_cursor();
+        let output = terminal.output();
+        assert!(output.ends_with("\x1b[1m\x1b[?25l"));
+        assert!(output.len() > "\x1b[1m\x1b[?25l".len());
+    }
+
+    #[test]
+    fn test_color_depth_from_env() {
+        fn depth(vars: &[(&str, &str)]) -> ColorDepth {
+            color_depth_from_env(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            })
+        }
+
+        assert_eq!(depth(&[("TERM", "dumb")]), ColorDepth::Monochrome);
+        assert_eq!(depth(&[("TERM", "linux")]), ColorDepth::Ansi16);
+        assert_eq!(depth(&[("TERM", "xterm")]), ColorDepth::Ansi16);
+        assert_eq!(depth(&[("TERM", "xterm-256color")]), ColorDepth::Ansi256);
+        assert_eq!(depth(&[("TERM", "xterm-direct")]), ColorDepth::TrueColor);
+        assert_eq!(
+            depth(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]),
+            ColorDepth::TrueColor
+        );
+    }
+
+    #[test]
+    fn test_color_depth() {
+        let orange = Color::Rgb {
+            r: 255,
+            g: 135,
+            b: 0,
+        };
+        let mut terminal = terminal();
+        terminal.set_color_depth(ColorDepth::Ansi16);
+        terminal.set_foreground_color(orange);
+        terminal.set_background_color(Color::Byte(4));
+        assert_eq!(terminal.output(), "\x1b[33m\x1b[44m");
+        // The color that was set is kept rather than the one it was reduced to
+        assert_eq!(terminal.current_style().fg, Some(orange));
+
+        terminal.set_color_depth(ColorDepth::TrueColor);
+        terminal.reset_colors();
+        terminal.output();
+        terminal.set_foreground_color(orange);
+        assert!(terminal.output().ends_with("255;135;0m"));
+
+        terminal.set_color_depth(ColorDepth::Monochrome);
+        assert!(!terminal.colors_enabled());
+        terminal.set_foreground_color(Color::Red);
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_colors_from_env() {
+        fn colors(vars: &[(&str, &str)]) -> bool {
+            colors_from_env(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            })
+        }
+
+        assert!(colors(&[]));
+        assert!(!colors(&[("NO_COLOR", "1")]));
+        assert!(colors(&[("NO_COLOR", "")]));
+        assert!(colors(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]));
+        assert!(!colors(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "0")]));
+        assert!(!colors(&[("CLICOLOR", "0")]));
+        assert!(colors(&[("CLICOLOR", "1")]));
+        assert!(colors(&[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")]));
+    }
+
+    #[test]
+    fn test_color_override() {
+        let mut terminal = terminal();
+        terminal.colors_from_env = false;
+
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_background_color(Color::Blue);
+        terminal.print_gradient("ab", Color::Red, Color::Blue);
+        terminal.set_style(Some(Color::Red), None, &[util::Attribute::Bold]);
+        assert_eq!(terminal.output(), "ab\u{1b}[1m");
+
+        terminal.set_color_override(Some(true));
+        terminal.set_foreground_color(Color::Red);
+        assert_eq!(terminal.output(), "\u{1b}[38;5;9m");
+
+        terminal.set_color_override(Some(false));
+        terminal.set_foreground_color(Color::Blue);
+        terminal.write("text");
+        assert_eq!(terminal.output(), "text");
+    }
+
+    #[test]
+    fn test_print_gradient() {
+        let mut terminal = terminal();
+
+        terminal.print_gradient("ab", Color::Red, Color::Blue);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[38;2;255;0;0ma\u{1b}[38;2;92;92;255mb"
+        );
+
+        // The color of the last character is already set
+        terminal.print_gradient("c", Color::Blue, Color::Blue);
+        assert_eq!(terminal.output(), "c");
+
+        // The wide character counts as two columns
+        terminal.print_gradient(
+            "a世b",
+            Color::Rgb { r: 0, g: 0, b: 0 },
+            Color::Rgb { r: 150, g: 0, b: 0 },
+        );
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[38;2;0;0;0ma\u{1b}[38;2;50;0;0m世\u{1b}[38;2;150;0;0mb"
+        );
+    }
 }
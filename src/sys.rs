@@ -1,7 +1,20 @@
-#[cfg(not(target_os = "redox"))]
+mod escape;
+#[cfg(any(test, feature = "minimal-unix"))]
+mod parser;
+
+#[cfg(not(any(
+    feature = "crossterm",
+    target_os = "redox",
+    all(unix, feature = "minimal-unix")
+)))]
+compile_error!("either the `crossterm` feature or, on Unix-like operating systems, the `minimal-unix` feature must be enabled");
+
+#[cfg(not(any(target_os = "redox", all(unix, feature = "minimal-unix"))))]
 mod other;
 #[cfg(target_os = "redox")]
 mod redox;
+#[cfg(all(unix, feature = "minimal-unix"))]
+mod unix;
 
 // #[cfg(target_os = "windows")]
 // mod other;
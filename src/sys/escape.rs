@@ -0,0 +1,863 @@
+//! Terminal functionality implemented by writing escape sequences directly, shared by all backends.
+
+use crate::{
+    image::base64,
+    util::{Attribute, Attributes, Color, ColorDepth, CursorShape, LineSize, Point, Style},
+    Terminal, REVERSE_SCREEN,
+};
+use std::{io::Write, sync::atomic::Ordering};
+
+impl<'a, W: Write> Terminal<'a, W> {
+    //
+    // TODO for the following methods: Do they work on Windows?
+    //
+
+    // Reference: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands
+    // NOTE: clipboard functionality can be added: https://github.com/alacritty/alacritty/blob/3e867a056018c507d79396cb5c5b4b8309c609c2/alacritty_terminal/src/ansi.rs#L440
+
+    /// Changes the terminal's foreground text color to `hex_color`.
+    ///
+    /// `hex_color` must be a hexadecimal color such as `"FF0000"`.
+    pub fn change_foreground_color(&mut self, hex_color: &str) {
+        self.write_escape_fmt(format_args!("\u{1b}]10;#{}\u{7}", hex_color));
+    }
+    pub fn reset_foreground_color(&mut self) {
+        self.write_escape("\u{1b}]110\u{7}");
+    }
+
+    /// Changes the terminal's background text color to `hex_color`.
+    ///
+    /// `hex_color` must be a hexadecimal color such as `FF0000`.
+    pub fn change_background_color(&mut self, hex_color: &str) {
+        self.write_escape_fmt(format_args!("\u{1b}]11;#{}\u{7}", hex_color));
+    }
+    pub fn reset_background_color(&mut self) {
+        self.write_escape("\u{1b}]111\u{7}");
+    }
+
+    /// Changes the terminal's cursor color to `hex_color`.
+    ///
+    /// `hex_color` must be a hexadecimal color such as `FF0000`.
+    pub fn change_cursor_color(&mut self, hex_color: &str) {
+        self.write_escape_fmt(format_args!("\u{1b}]12;#{}\u{7}", hex_color));
+    }
+    pub fn reset_cursor_color(&mut self) {
+        self.write_escape("\u{1b}]112\u{7}");
+    }
+
+    /// Redefines the entry `index` of the terminal's 256-color palette as `color`,
+    /// which changes how [`Color::Byte(index)`](Color::Byte) and, for `index` < 16, the corresponding 4-bit color look.
+    ///
+    /// 4-bit and 8-bit colors are resolved using [`Color::to_rgb`].
+    pub fn set_palette_color(&mut self, index: u8, color: Color) {
+        let (r, g, b) = color.to_rgb();
+        self.write_escape_fmt(format_args!(
+            "\u{1b}]4;{};rgb:{:02x}/{:02x}/{:02x}\u{7}",
+            index, r, g, b
+        ));
+        self.palette.insert(index, color);
+    }
+    /// Resets the entry `index` of the terminal's 256-color palette to its default.
+    pub fn reset_palette_color(&mut self, index: u8) {
+        self.write_escape_fmt(format_args!("\u{1b}]104;{}\u{7}", index));
+        self.palette.remove(&index);
+    }
+    /// Resets all entries of the terminal's 256-color palette to their defaults.
+    pub fn reset_all_palette_colors(&mut self) {
+        self.write_escape("\u{1b}]104\u{7}");
+        self.palette.clear();
+    }
+    /// Returns the color the entry `index` of the palette has been redefined as
+    /// using [`set_palette_color`](Self::set_palette_color), if it has been.
+    pub fn palette_color(&self, index: u8) -> Option<Color> {
+        self.palette.get(&index).copied()
+    }
+
+    /// Marks the start of a prompt (OSC 133 `A`) so that the terminal can, e.g., navigate between commands.
+    ///
+    /// Together with the other `mark_` methods, this is known as shell integration.
+    /// Terminals that don't support it ignore the marks.
+    pub fn mark_prompt_start(&mut self) {
+        self.write_escape("\u{1b}]133;A\u{7}");
+    }
+    /// Marks the start of the command the user types after a prompt (OSC 133 `B`).
+    pub fn mark_command_start(&mut self) {
+        self.write_escape("\u{1b}]133;B\u{7}");
+    }
+    /// Marks the start of the output of a command (OSC 133 `C`).
+    pub fn mark_command_output_start(&mut self) {
+        self.write_escape("\u{1b}]133;C\u{7}");
+    }
+    /// Marks the end of a command (OSC 133 `D`), with its exit code if it's known.
+    pub fn mark_command_finished(&mut self, exit_code: Option<i32>) {
+        match exit_code {
+            Some(exit_code) => {
+                self.write_escape_fmt(format_args!("\u{1b}]133;D;{}\u{7}", exit_code))
+            }
+            None => self.write_escape("\u{1b}]133;D\u{7}"),
+        }
+    }
+
+    /// Sets the badge, a large label shown in the top right corner of the terminal, to `text`.
+    /// An empty `text` removes the badge.
+    ///
+    /// This is specific to iTerm2; other terminals ignore it.
+    pub fn set_badge(&mut self, text: &str) {
+        self.write_escape_fmt(format_args!(
+            "\u{1b}]1337;SetBadgeFormat={}\u{7}",
+            base64(text.as_bytes())
+        ));
+    }
+
+    /// Requests the user's attention, e.g. by bouncing the Dock icon.
+    /// If `persistent` is `true`, this continues until the terminal is focused.
+    ///
+    /// This is specific to iTerm2; other terminals ignore it.
+    pub fn request_attention(&mut self, persistent: bool) {
+        self.write_escape_fmt(format_args!(
+            "\u{1b}]1337;RequestAttention={}\u{7}",
+            persistent
+        ));
+    }
+
+    /// Resets the foreground color of the following text to the terminal's default, leaving the attributes as they are.
+    ///
+    /// Unlike [`reset_foreground_color`](Self::reset_foreground_color), this doesn't change the terminal's default.
+    pub fn reset_foreground_color_only(&mut self) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape("\u{1b}[39m");
+        self.foreground_color = None;
+    }
+    /// Resets the background color of the following text to the terminal's default, leaving the attributes as they are.
+    ///
+    /// Unlike [`reset_background_color`](Self::reset_background_color), this doesn't change the terminal's default.
+    pub fn reset_background_color_only(&mut self) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape("\u{1b}[49m");
+        self.background_color = None;
+    }
+
+    /// Sets the attributes of the following text.
+    ///
+    /// Only the attributes that differ from the ones currently set are enabled or disabled.
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        let parameters: Vec<String> = self
+            .attribute_parameters(attributes)
+            .iter()
+            .map(u8::to_string)
+            .collect();
+        self.write_sgr(&parameters);
+        self.attributes = attributes;
+    }
+
+    /// Sets the foreground color, the background color and the attributes of the following text
+    /// using a single escape sequence, which is shorter than setting each of them separately.
+    ///
+    /// Colors that are `None` are left as they are. The attributes are set like using
+    /// [`set_attributes`](Self::set_attributes), which means that other attributes are disabled.
+    /// Only what differs from what is currently set is written.
+    pub fn set_style(&mut self, fg: Option<Color>, bg: Option<Color>, attributes: &[Attribute]) {
+        self.apply_style(&Style {
+            fg: fg.or(self.foreground_color),
+            bg: bg.or(self.background_color),
+            attributes: Attributes::from(attributes),
+        });
+    }
+
+    /// Returns the colors and attributes currently set, for example to restore them later using
+    /// [`apply_style`](Self::apply_style).
+    pub fn current_style(&self) -> Style {
+        Style {
+            fg: self.foreground_color,
+            bg: self.background_color,
+            attributes: self.attributes,
+        }
+    }
+
+    /// Sets the colors and attributes of the following text to `style` using a single escape sequence.
+    ///
+    /// Only what differs from what is currently set is written.
+    /// Colors that are `None` are reset to the terminal's default. Colors are left as they are if they are disabled.
+    pub fn apply_style(&mut self, style: &Style) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        let mut parameters: Vec<String> = self
+            .attribute_parameters(style.attributes)
+            .iter()
+            .map(u8::to_string)
+            .collect();
+        let (fg, bg) = if self.colors_enabled() {
+            (style.fg, style.bg)
+        } else {
+            (self.foreground_color, self.background_color)
+        };
+        parameters.extend(color_parameter(
+            self.foreground_color,
+            fg,
+            38,
+            self.capabilities.color_depth,
+        ));
+        parameters.extend(color_parameter(
+            self.background_color,
+            bg,
+            48,
+            self.capabilities.color_depth,
+        ));
+        self.write_sgr(&parameters);
+
+        self.foreground_color = fg;
+        self.background_color = bg;
+        self.attributes = style.attributes;
+    }
+
+    /// Returns the SGR parameters that change the attributes currently set to `attributes`.
+    fn attribute_parameters(&self, attributes: Attributes) -> Vec<u8> {
+        let current = self.attributes;
+        let mut parameters = Vec::new();
+
+        for attribute in current.iter() {
+            if !attributes.contains(attribute) && !parameters.contains(&attribute.reset_sgr()) {
+                parameters.push(attribute.reset_sgr());
+            }
+        }
+        // Bold and dim are disabled together so one of them might have to be enabled again
+        let bold_and_dim_disabled = parameters.contains(&Attribute::Bold.reset_sgr());
+        for attribute in attributes.iter() {
+            let disabled =
+                bold_and_dim_disabled && attribute.reset_sgr() == Attribute::Bold.reset_sgr();
+            if !current.contains(attribute) || disabled {
+                parameters.push(attribute.sgr());
+            }
+        }
+
+        parameters
+    }
+
+    /// Queues an SGR sequence with `parameters`, unless there are none.
+    fn write_sgr(&mut self, parameters: &[String]) {
+        if !parameters.is_empty() {
+            self.write_escape(&format!("\u{1b}[{}m", parameters.join(";")));
+        }
+    }
+
+    /// Moves the cursor to the top left corner (CUP without parameters).
+    ///
+    /// This is shorter than [`set_cursor`](Self::set_cursor) with a point of `(0, 0)`
+    /// because no coordinates are written.
+    pub fn cursor_home(&mut self) {
+        self.write_escape("\u{1b}[H");
+        self.cursor = Some(Point::default());
+    }
+
+    /// Sets the size of the characters of the line the cursor is on, e.g. for banner headings.
+    ///
+    /// On a line with double-width characters, every cell is twice as wide, so only half as many columns fit on it
+    /// and the cursor's column refers to those wider cells.
+    /// Support is patchy: many terminals ignore this or only support some of the sizes.
+    pub fn set_line_size(&mut self, size: LineSize) {
+        self.write_escape(match size {
+            LineSize::Normal => "\u{1b}#5",
+            LineSize::DoubleWidth => "\u{1b}#6",
+            LineSize::DoubleHeightTop => "\u{1b}#3",
+            LineSize::DoubleHeightBottom => "\u{1b}#4",
+        });
+    }
+
+    /// Sets the shape of the cursor and whether it blinks (DECSCUSR).
+    ///
+    /// Not all terminals support this. See [`cursor_shape_supported`](Self::cursor_shape_supported).
+    pub fn set_cursor_shape(&mut self, shape: CursorShape, blinking: bool) {
+        let parameter = match shape {
+            CursorShape::Block => 1,
+            CursorShape::Underline => 3,
+            CursorShape::Bar => 5,
+        } + !blinking as u8;
+        self.write_escape(&format!("\u{1b}[{} q", parameter));
+    }
+    /// Resets the shape of the cursor to the one the user configured.
+    ///
+    /// This should be done before exiting if the shape has been set using [`set_cursor_shape`](Self::set_cursor_shape)
+    /// because the shape isn't reset when leaving the alternate screen.
+    pub fn reset_cursor_shape(&mut self) {
+        self.write_escape("\u{1b}[0 q");
+    }
+
+    /// Returns whether the terminal is likely to support setting the cursor shape.
+    ///
+    /// This is a guess based on the `TERM` environment variable, which only includes terminals known to support it.
+    pub fn cursor_shape_supported(&self) -> bool {
+        self.tty && std::env::var("TERM").is_ok_and(|term| cursor_shape_supported_by(&term))
+    }
+
+    /// Makes the cursor blink.
+    ///
+    /// Cursor shapes set using [`set_cursor_shape`](Self::set_cursor_shape) also determine whether the cursor blinks.
+    /// In most terminals, whichever of the two is set last takes precedence.
+    pub fn enable_cursor_blink(&mut self) {
+        self.write_escape("\u{1b}[?12h");
+    }
+    /// Makes the cursor stop blinking.
+    ///
+    /// See [`enable_cursor_blink`](Self::enable_cursor_blink) for how this interacts with cursor shapes.
+    pub fn disable_cursor_blink(&mut self) {
+        self.write_escape("\u{1b}[?12l");
+    }
+
+    /// Enables alternate scroll mode, in which scrolling the mouse wheel in the alternate screen
+    /// is reported as [`Key::Up`](crate::event::Key::Up) and [`Key::Down`](crate::event::Key::Down) presses.
+    ///
+    /// This only has an effect while mouse capture is disabled.
+    /// With mouse capture enabled, scrolling is reported as mouse events instead.
+    pub fn enable_alternate_scroll(&mut self) {
+        self.write_escape("\u{1b}[?1007h");
+    }
+    /// Disables alternate scroll mode.
+    pub fn disable_alternate_scroll(&mut self) {
+        self.write_escape("\u{1b}[?1007l");
+    }
+
+    /// Enables reverse screen mode (DECSCNM), in which the terminal swaps the foreground and background colors
+    /// of the whole screen, e.g. for a high-contrast mode.
+    ///
+    /// Unlike [`Attribute::Reversed`], this is not a property of the text written but a mode of the terminal,
+    /// which stays enabled after the program exits. Like mouse capture, it's disabled again by
+    /// [`deinitialize`](Self::deinitialize) and, if the program panics after [`initialize`](Self::initialize),
+    /// before the panic message is printed. Does nothing if it's already enabled.
+    pub fn enable_reverse_screen(&mut self) {
+        if !self.reverse_screen {
+            self.write_escape("\u{1b}[?5h");
+            self.set_reverse_screen(true);
+        }
+    }
+    /// Disables reverse screen mode. Does nothing if it's not enabled.
+    pub fn disable_reverse_screen(&mut self) {
+        if self.reverse_screen {
+            self.write_escape("\u{1b}[?5l");
+            self.set_reverse_screen(false);
+        }
+    }
+
+    /// Makes text that reaches the end of a line continue on the next line (DECAWM), which is the default.
+    pub fn enable_line_wrap(&mut self) {
+        self.write_escape("\u{1b}[?7h");
+        self.line_wrap = true;
+    }
+    /// Makes text that reaches the end of a line overwrite the last column instead of continuing on the next line.
+    pub fn disable_line_wrap(&mut self) {
+        self.write_escape("\u{1b}[?7l");
+        self.line_wrap = false;
+    }
+
+    /// Keeps track of whether reverse screen mode is enabled, including for the panic hook.
+    fn set_reverse_screen(&mut self, enabled: bool) {
+        self.reverse_screen = enabled;
+        if self.tty {
+            REVERSE_SCREEN.store(enabled, Ordering::Relaxed);
+        }
+    }
+
+    /// Begins a synchronized update, during which the terminal keeps displaying what it did before
+    /// until [`end_synchronized_update`](Self::end_synchronized_update) so that partially drawn frames are not visible.
+    ///
+    /// Terminals that don't support synchronized updates ignore this.
+    /// Also see [`frame`](Self::frame).
+    pub fn begin_synchronized_update(&mut self) {
+        self.write_escape("\u{1b}[?2026h");
+    }
+    /// Ends a synchronized update, after which the terminal displays everything written during it at once.
+    pub fn end_synchronized_update(&mut self) {
+        self.write_escape("\u{1b}[?2026l");
+    }
+
+    /// Sets a tab stop at the cursor's column (HTS).
+    ///
+    /// Writing `\t` moves the cursor to the next tab stop, so this changes how tabs are rendered.
+    /// By default, there is a tab stop every 8 columns.
+    pub fn set_tab_stop(&mut self) {
+        self.write_escape("\u{1b}H");
+    }
+    /// Clears the tab stop at the cursor's column (TBC).
+    pub fn clear_tab_stop(&mut self) {
+        self.write_escape("\u{1b}[g");
+    }
+    /// Clears all tab stops (TBC), after which `\t` moves the cursor to the end of the line.
+    pub fn clear_all_tab_stops(&mut self) {
+        self.write_escape("\u{1b}[3g");
+    }
+
+    /// Makes mouse positions be reported in pixels (SGR-Pixels) in addition to cells,
+    /// as [`MouseEvent::pixel`](crate::event::MouseEvent::pixel).
+    /// This requires mouse capture to be enabled.
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    pub fn enable_mouse_pixels(&mut self) {
+        self.write_escape("\u{1b}[?1016h");
+        self.mouse_pixels = true;
+    }
+    /// Makes mouse positions be reported only in cells again.
+    pub fn disable_mouse_pixels(&mut self) {
+        self.write_escape("\u{1b}[?1016l");
+        self.mouse_pixels = false;
+    }
+
+    /// Enables application keypad mode (DECKPAM),
+    /// in which keys on the numeric keypad are reported as [`Key::Keypad`](crate::event::Key::Keypad).
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// With crossterm, keypad keys are not reported at all while this is enabled.
+    pub fn enable_application_keypad(&mut self) {
+        self.write_escape("\u{1b}=");
+    }
+    /// Disables application keypad mode (DECKPNM).
+    pub fn disable_application_keypad(&mut self) {
+        self.write_escape("\u{1b}>");
+    }
+
+    /// Enables xterm's modifyOtherKeys mode, in which keys pressed with modifiers that can't be told apart otherwise,
+    /// such as Ctrl+; or Ctrl+Enter, are reported with their modifiers.
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// Terminals that don't support the mode ignore this.
+    /// Also see [`enable_kitty_keyboard`](Self::enable_kitty_keyboard), which some terminals support instead.
+    pub fn enable_modify_other_keys(&mut self) {
+        self.write_escape("\u{1b}[>4;2m");
+    }
+    /// Disables xterm's modifyOtherKeys mode.
+    pub fn disable_modify_other_keys(&mut self) {
+        self.write_escape("\u{1b}[>4m");
+    }
+
+    /// Enables the Kitty keyboard protocol, with which key repeats and releases are reported
+    /// in addition to presses, and with which keypad keys are reported as [`Key::Keypad`](crate::event::Key::Keypad).
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// Terminals that don't support the protocol ignore this.
+    /// Repeats and releases must be requested using [`set_report_key_repeats`](Self::set_report_key_repeats)
+    /// and [`set_report_key_releases`](Self::set_report_key_releases) as well.
+    pub fn enable_kitty_keyboard(&mut self) {
+        // Disambiguate escape codes and report event types
+        self.write_escape("\u{1b}[>3u");
+    }
+    /// Disables the Kitty keyboard protocol.
+    pub fn disable_kitty_keyboard(&mut self) {
+        self.write_escape("\u{1b}[<u");
+    }
+
+    /// Performs a soft terminal reset (DECSTR).
+    ///
+    /// Unlike [`reset_colors`](Self::reset_colors), which only resets the colors,
+    /// this also resets all text attributes, the scrolling margins,
+    /// and modes such as insert mode, origin mode and the cursor visibility back to their defaults.
+    /// The saved cursor point is reset to the top left corner.
+    ///
+    /// It does not clear the screen, move the cursor or leave the alternate screen.
+    pub fn soft_reset(&mut self) {
+        self.write_escape("\u{1b}[!p");
+        self.saved_cursor = Some(Point::default());
+        self.cursor_visible = true;
+        self.line_wrap = true;
+        self.foreground_color = None;
+        self.background_color = None;
+        self.attributes = Attributes::empty();
+    }
+
+    /// Performs a full terminal reset (RIS).
+    ///
+    /// This resets the terminal to its initial state: the screen is cleared, the cursor is moved to the top left corner
+    /// and all modes, including mouse capture and the alternate screen, are reset.
+    /// Whether the scrollback is cleared as well depends on the terminal.
+    ///
+    /// Any state this terminal keeps track of is invalidated too.
+    pub fn hard_reset(&mut self) {
+        self.write_escape("\u{1b}c");
+        self.cursor = Some(Point::default());
+        self.saved_cursor = None;
+        self.with_mouse = false;
+        self.mouse_pixels = false;
+        self.set_reverse_screen(false);
+        self.cursor_visible = true;
+        self.line_wrap = true;
+        self.palette.clear();
+        self.foreground_color = None;
+        self.background_color = None;
+        self.attributes = Attributes::empty();
+        self.buffer.invalidate();
+    }
+}
+
+/// Returns whether the terminal `term`, as named by the `TERM` environment variable,
+/// is known to support setting the cursor shape.
+fn cursor_shape_supported_by(term: &str) -> bool {
+    // Most terminal emulators identify themselves as xterm
+    const TERMS: [&str; 7] = [
+        "xterm",
+        "kitty",
+        "alacritty",
+        "foot",
+        "wezterm",
+        "tmux",
+        "rxvt-unicode",
+    ];
+    TERMS.iter().any(|prefix| term.starts_with(prefix))
+}
+
+/// Returns the SGR parameter changing a color from `current` to `target`, if they differ.
+///
+/// `layer` is 38 for the foreground color and 48 for the background color. The color is reduced to `depth`.
+fn color_parameter(
+    current: Option<Color>,
+    target: Option<Color>,
+    layer: u8,
+    depth: ColorDepth,
+) -> Option<String> {
+    match target {
+        _ if current == target => None,
+        Some(color) => Some(color.sgr(layer, depth)),
+        // The parameter after the ones for setting 8-bit and 24-bit colors resets to the default color
+        None => Some((layer + 1).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    fn terminal() -> Terminal<'static, Vec<u8>> {
+        Terminal::test(Size {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    #[test]
+    fn test_soft_reset() {
+        let mut terminal = terminal();
+        terminal.set_foreground_color(Color::Red);
+        terminal.output();
+        terminal.soft_reset();
+        assert_eq!(terminal.output(), "\u{1b}[!p");
+        terminal.set_foreground_color(Color::Red);
+        assert_eq!(terminal.output(), "\u{1b}[38;5;9m");
+    }
+
+    #[test]
+    fn test_shell_integration_marks() {
+        let mut terminal = terminal();
+        terminal.mark_prompt_start();
+        assert_eq!(terminal.output(), "\u{1b}]133;A\u{7}");
+        terminal.mark_command_start();
+        assert_eq!(terminal.output(), "\u{1b}]133;B\u{7}");
+        terminal.mark_command_output_start();
+        assert_eq!(terminal.output(), "\u{1b}]133;C\u{7}");
+        terminal.mark_command_finished(None);
+        assert_eq!(terminal.output(), "\u{1b}]133;D\u{7}");
+        terminal.mark_command_finished(Some(-1));
+        assert_eq!(terminal.output(), "\u{1b}]133;D;-1\u{7}");
+    }
+
+    #[test]
+    fn test_palette_colors() {
+        let mut terminal = terminal();
+
+        terminal.set_palette_color(
+            4,
+            Color::Rgb {
+                r: 18,
+                g: 52,
+                b: 171,
+            },
+        );
+        assert_eq!(terminal.output(), "\u{1b}]4;4;rgb:12/34/ab\u{7}");
+        terminal.set_palette_color(200, Color::Red);
+        assert_eq!(terminal.output(), "\u{1b}]4;200;rgb:ff/00/00\u{7}");
+        assert_eq!(terminal.palette_color(200), Some(Color::Red));
+
+        terminal.reset_palette_color(200);
+        assert_eq!(terminal.output(), "\u{1b}]104;200\u{7}");
+        assert_eq!(terminal.palette_color(200), None);
+        assert!(terminal.palette_color(4).is_some());
+
+        terminal.reset_all_palette_colors();
+        assert_eq!(terminal.output(), "\u{1b}]104\u{7}");
+        assert_eq!(terminal.palette_color(4), None);
+    }
+
+    #[test]
+    fn test_reset_color_only() {
+        let mut terminal = terminal();
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_background_color(Color::Blue);
+        terminal.set_attributes(Attributes::from(&[Attribute::Bold][..]));
+        terminal.output();
+
+        terminal.reset_foreground_color_only();
+        assert_eq!(terminal.output(), "\u{1b}[39m");
+        assert_eq!(
+            terminal.current_style(),
+            Style {
+                fg: None,
+                bg: Some(Color::Blue),
+                attributes: Attributes::from(&[Attribute::Bold][..]),
+            }
+        );
+
+        terminal.reset_background_color_only();
+        assert_eq!(terminal.output(), "\u{1b}[49m");
+        assert_eq!(terminal.current_style().bg, None);
+        assert!(terminal
+            .current_style()
+            .attributes
+            .contains(Attribute::Bold));
+
+        // The color has to be set again
+        terminal.set_foreground_color(Color::Red);
+        assert_eq!(terminal.output(), "\u{1b}[38;5;9m");
+    }
+
+    #[test]
+    fn test_set_attributes() {
+        let mut terminal = terminal();
+        terminal.set_attributes(Attributes::from(&[Attribute::Bold, Attribute::Dim][..]));
+        assert_eq!(terminal.output(), "\u{1b}[1;2m");
+        terminal.set_attributes(Attributes::from(&[Attribute::Dim, Attribute::Italic][..]));
+        assert_eq!(terminal.output(), "\u{1b}[22;2;3m");
+        terminal.set_attributes(Attributes::from(&[Attribute::Dim, Attribute::Italic][..]));
+        assert_eq!(terminal.output(), "");
+        terminal.set_attributes(Attributes::empty());
+        assert_eq!(terminal.output(), "\u{1b}[22;23m");
+    }
+
+    #[test]
+    fn test_set_style() {
+        let mut combined_terminal = terminal();
+        combined_terminal.enable_italic();
+        combined_terminal.set_foreground_color(Color::Red);
+        combined_terminal.output();
+
+        combined_terminal.set_style(Some(Color::Red), Some(Color::Blue), &[Attribute::Bold]);
+        let combined = combined_terminal.output();
+        assert_eq!(combined, "\u{1b}[23;1;48;5;12m");
+
+        let mut separate_terminal = terminal();
+        separate_terminal.enable_italic();
+        separate_terminal.set_foreground_color(Color::Red);
+        separate_terminal.output();
+        separate_terminal.set_attributes(Attributes::from(&[Attribute::Bold][..]));
+        separate_terminal.set_background_color(Color::Blue);
+        let separate = separate_terminal.output();
+        assert_eq!(separate, "\u{1b}[23;1m\u{1b}[48;5;12m");
+        assert!(combined.len() < separate.len());
+
+        // Nothing changed
+        combined_terminal.set_style(None, Some(Color::Blue), &[Attribute::Bold]);
+        assert_eq!(combined_terminal.output(), "");
+        combined_terminal.set_foreground_color(Color::Red);
+        assert_eq!(combined_terminal.output(), "");
+    }
+
+    #[test]
+    fn test_apply_style() {
+        let mut terminal = terminal();
+        terminal.set_style(Some(Color::Red), None, &[Attribute::Bold]);
+        terminal.output();
+
+        let style = terminal.current_style();
+        terminal.apply_style(&Style {
+            fg: Some(Color::Blue),
+            bg: Some(Color::Black),
+            attributes: Attributes::from(&[Attribute::Italic][..]),
+        });
+        terminal.write("child");
+        terminal.apply_style(&style);
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}[22;3;38;5;12;48;5;0mchild\u{1b}[23;1;38;5;9;49m"
+        );
+        assert_eq!(terminal.current_style(), style);
+
+        terminal.apply_style(&style);
+        assert_eq!(terminal.output(), "");
+    }
+
+    #[test]
+    fn test_cursor_home() {
+        let mut terminal = terminal();
+        terminal.set_cursor(Point { x: 0, y: 0 });
+        let set_cursor = terminal.output();
+        terminal.cursor_home();
+        let cursor_home = terminal.output();
+        assert_eq!(cursor_home, "\u{1b}[H");
+        assert!(cursor_home.len() < set_cursor.len());
+    }
+
+    #[test]
+    fn test_cursor_shape() {
+        let mut terminal = terminal();
+        terminal.set_cursor_shape(CursorShape::Block, true);
+        terminal.set_cursor_shape(CursorShape::Underline, false);
+        terminal.set_cursor_shape(CursorShape::Bar, false);
+        assert_eq!(terminal.output(), "\u{1b}[1 q\u{1b}[4 q\u{1b}[6 q");
+        terminal.reset_cursor_shape();
+        assert_eq!(terminal.output(), "\u{1b}[0 q");
+    }
+
+    #[test]
+    fn test_cursor_shape_supported_by() {
+        assert!(cursor_shape_supported_by("xterm-256color"));
+        assert!(cursor_shape_supported_by("xterm-kitty"));
+        assert!(cursor_shape_supported_by("tmux-256color"));
+        assert!(cursor_shape_supported_by("foot"));
+        assert!(!cursor_shape_supported_by("linux"));
+        assert!(!cursor_shape_supported_by("vt100"));
+        assert!(!cursor_shape_supported_by("dumb"));
+        assert!(!cursor_shape_supported_by(""));
+    }
+
+    #[test]
+    fn test_cursor_blink() {
+        let mut terminal = terminal();
+        terminal.enable_cursor_blink();
+        assert_eq!(terminal.output(), "\u{1b}[?12h");
+        terminal.disable_cursor_blink();
+        assert_eq!(terminal.output(), "\u{1b}[?12l");
+    }
+
+    #[test]
+    fn test_alternate_scroll() {
+        let mut terminal = terminal();
+        terminal.enable_alternate_scroll();
+        assert_eq!(terminal.output(), "\u{1b}[?1007h");
+        terminal.disable_alternate_scroll();
+        assert_eq!(terminal.output(), "\u{1b}[?1007l");
+    }
+
+    #[test]
+    fn test_reverse_screen() {
+        let mut terminal = terminal();
+        terminal.enable_reverse_screen();
+        assert_eq!(terminal.output(), "\u{1b}[?5h");
+        terminal.disable_reverse_screen();
+        assert_eq!(terminal.output(), "\u{1b}[?5l");
+        terminal.disable_reverse_screen();
+        assert_eq!(terminal.output(), "");
+
+        // It's disabled when the terminal is handed back
+        terminal.initialized = true;
+        terminal.enable_reverse_screen();
+        terminal.output();
+        terminal.deinitialize();
+        assert!(terminal.output().contains("\u{1b}[?5l"));
+        assert!(!terminal.reverse_screen);
+    }
+
+    #[test]
+    fn test_synchronized_update() {
+        let mut terminal = terminal();
+        terminal.begin_synchronized_update();
+        assert_eq!(terminal.output(), "\u{1b}[?2026h");
+        terminal.end_synchronized_update();
+        assert_eq!(terminal.output(), "\u{1b}[?2026l");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut terminal = terminal();
+        terminal.set_tab_stop();
+        assert_eq!(terminal.output(), "\u{1b}H");
+        terminal.clear_tab_stop();
+        assert_eq!(terminal.output(), "\u{1b}[g");
+        terminal.clear_all_tab_stops();
+        assert_eq!(terminal.output(), "\u{1b}[3g");
+    }
+
+    #[test]
+    fn test_mouse_pixels() {
+        let mut terminal = terminal();
+        terminal.enable_mouse_pixels();
+        assert_eq!(terminal.output(), "\u{1b}[?1016h");
+        assert!(terminal.mouse_pixels);
+        terminal.disable_mouse_pixels();
+        assert_eq!(terminal.output(), "\u{1b}[?1016l");
+        assert!(!terminal.mouse_pixels);
+    }
+
+    #[test]
+    fn test_application_keypad() {
+        let mut terminal = terminal();
+        terminal.enable_application_keypad();
+        assert_eq!(terminal.output(), "\u{1b}=");
+        terminal.disable_application_keypad();
+        assert_eq!(terminal.output(), "\u{1b}>");
+    }
+
+    #[test]
+    fn test_iterm2() {
+        let mut terminal = terminal();
+        terminal.set_badge("build ✓");
+        assert_eq!(
+            terminal.output(),
+            "\u{1b}]1337;SetBadgeFormat=YnVpbGQg4pyT\u{7}"
+        );
+        terminal.set_badge("");
+        assert_eq!(terminal.output(), "\u{1b}]1337;SetBadgeFormat=\u{7}");
+        terminal.request_attention(true);
+        assert_eq!(terminal.output(), "\u{1b}]1337;RequestAttention=true\u{7}");
+        terminal.request_attention(false);
+        assert_eq!(terminal.output(), "\u{1b}]1337;RequestAttention=false\u{7}");
+    }
+
+    #[test]
+    fn test_set_line_size() {
+        let mut terminal = terminal();
+        terminal.set_line_size(LineSize::Normal);
+        assert_eq!(terminal.output(), "\u{1b}#5");
+        terminal.set_line_size(LineSize::DoubleWidth);
+        assert_eq!(terminal.output(), "\u{1b}#6");
+        terminal.set_line_size(LineSize::DoubleHeightTop);
+        assert_eq!(terminal.output(), "\u{1b}#3");
+        terminal.set_line_size(LineSize::DoubleHeightBottom);
+        assert_eq!(terminal.output(), "\u{1b}#4");
+    }
+
+    #[test]
+    fn test_modify_other_keys() {
+        let mut terminal = terminal();
+        terminal.enable_modify_other_keys();
+        assert_eq!(terminal.output(), "\u{1b}[>4;2m");
+        terminal.disable_modify_other_keys();
+        assert_eq!(terminal.output(), "\u{1b}[>4m");
+    }
+
+    #[test]
+    fn test_kitty_keyboard() {
+        let mut terminal = terminal();
+        terminal.enable_kitty_keyboard();
+        assert_eq!(terminal.output(), "\u{1b}[>3u");
+        terminal.disable_kitty_keyboard();
+        assert_eq!(terminal.output(), "\u{1b}[<u");
+    }
+
+    #[test]
+    fn test_hard_reset() {
+        let mut terminal = terminal();
+        terminal.enable_mouse_capture();
+        terminal.set_foreground_color(Color::Red);
+        terminal.set_background_color(Color::Blue);
+        terminal.output();
+        terminal.hard_reset();
+        assert_eq!(terminal.output(), "\u{1b}c");
+        assert!(!terminal.with_mouse);
+        assert!(!terminal.mouse_pixels);
+        assert_eq!(terminal.foreground_color, None);
+        assert_eq!(terminal.background_color, None);
+    }
+}
@@ -1,12 +1,19 @@
 //! Terminal implementation for all non-Redox operating systems.
 
 use crate::{
-    event::{Event, Key, MouseButton, MouseEvent, MouseEventKind},
-    util::{Color, Point, Size},
+    cursor::Direction,
+    event::{
+        Event, Key, KeyEvent, KeyEventKind, KeyModifier, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
+    util::{Attribute, Attributes, Color, ColorDepth, Point, Size},
     Terminal,
 };
 use crossterm::{cursor, event, style, terminal, tty::IsTty, QueueableCommand};
-use std::{io, time::Duration};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
 
 // TODO: return result instead of unwrapping?
 
@@ -15,127 +22,164 @@ use std::{io, time::Duration};
 // > Luckily, I could work around this by just checking if we were already using the color I wanted to render.
 // > If we were, I didn't set the color again.
 
-impl<'a> Terminal<'a> {
+impl<'a, W: Write> Terminal<'a, W> {
     pub fn enter_alternate_dimension(&mut self) {
-        self.stdout.queue(terminal::EnterAlternateScreen).unwrap();
+        self.queue_command(terminal::EnterAlternateScreen);
+        self.cursor = None;
     }
     pub fn exit_alternate_dimension(&mut self) {
-        self.stdout.queue(terminal::LeaveAlternateScreen).unwrap();
+        self.queue_command(terminal::LeaveAlternateScreen);
+        self.cursor = None;
     }
 
     pub fn set_title(&mut self, title: &str) {
-        self.stdout.queue(terminal::SetTitle(title)).unwrap();
+        self.queue_command(terminal::SetTitle(title));
     }
 
-    pub fn enable_raw_mode(&self) {
-        terminal::enable_raw_mode().unwrap();
+    pub fn enable_raw_mode(&mut self) {
+        if self.tty {
+            terminal::enable_raw_mode().unwrap();
+        }
+        self.raw_mode = true;
     }
-    pub fn disable_raw_mode(&self) {
-        terminal::disable_raw_mode().unwrap();
+    pub fn disable_raw_mode(&mut self) {
+        if self.tty {
+            terminal::disable_raw_mode().unwrap();
+        }
+        self.raw_mode = false;
     }
 
     // TODO: use custom escape sequence to be more specific about what mouse events exactly to take
+    /// Enables mouse capture so that mouse events are reported. Does nothing if it's already enabled.
+    ///
+    /// See also [`capture_mouse`](Self::capture_mouse).
     pub fn enable_mouse_capture(&mut self) {
-        self.stdout.queue(event::EnableMouseCapture).unwrap();
+        if self.with_mouse {
+            return;
+        }
+        self.queue_command(event::EnableMouseCapture);
         self.with_mouse = true;
     }
+    /// Disables mouse capture. Does nothing if it's already disabled.
     pub fn disable_mouse_capture(&mut self) {
-        self.stdout.queue(event::DisableMouseCapture).unwrap();
+        if !self.with_mouse {
+            return;
+        }
+        self.queue_command(event::DisableMouseCapture);
         self.with_mouse = false;
     }
 
+    /// Shows the cursor. Does nothing if it's already visible.
     pub fn show_cursor(&mut self) {
-        self.stdout.queue(cursor::Show).unwrap();
+        if !self.cursor_visible && self.capabilities.escapes {
+            self.queue_command(cursor::Show);
+            self.cursor_visible = true;
+        }
     }
+    /// Hides the cursor. Does nothing if it's already hidden.
     pub fn hide_cursor(&mut self) {
-        self.stdout.queue(cursor::Hide).unwrap();
+        if self.cursor_visible && self.capabilities.escapes {
+            self.queue_command(cursor::Hide);
+            self.cursor_visible = false;
+        }
     }
 
     /// Reads an event. It also sets the new size if the terminal has been resized, hence a mutable borrow of `self` is required.
     pub fn read_event(&mut self) -> Option<Event> {
-        if let Ok(crossterm_event) = event::read() {
-            let event = match crossterm_event {
-                event::Event::Mouse(event) => {
-                    fn translate_button(button: event::MouseButton) -> MouseButton {
-                        match button {
-                            event::MouseButton::Left => MouseButton::Left,
-                            event::MouseButton::Middle => MouseButton::Middle,
-                            event::MouseButton::Right => MouseButton::Right,
-                        }
-                    }
-
-                    let kind = match event.kind {
-                        event::MouseEventKind::Moved => MouseEventKind::Move,
-                        event::MouseEventKind::Drag(button) => {
-                            MouseEventKind::Drag(translate_button(button))
-                        }
-                        event::MouseEventKind::Down(button) => {
-                            MouseEventKind::Press(translate_button(button))
-                        }
-                        event::MouseEventKind::Up(button) => {
-                            MouseEventKind::Release(translate_button(button))
-                        }
-                        event::MouseEventKind::ScrollUp => MouseEventKind::ScrollUp,
-                        event::MouseEventKind::ScrollDown => MouseEventKind::ScrollDown,
-                    };
-
-                    let point = Point {
-                        x: event.column,
-                        y: event.row,
-                    };
-
-                    Event::Mouse(MouseEvent { kind, point })
-                }
-                event::Event::Key(event::KeyEvent { code, modifiers: _ }) => {
-                    let key = match code {
-                        event::KeyCode::Char(char) => Key::Char(char),
-                        event::KeyCode::Up => Key::Up,
-                        event::KeyCode::Down => Key::Down,
-                        event::KeyCode::Left => Key::Left,
-                        event::KeyCode::Right => Key::Right,
-                        event::KeyCode::Tab => Key::Tab,
-                        event::KeyCode::Enter => Key::Enter,
-                        event::KeyCode::F(number) => Key::F(number),
-                        event::KeyCode::Backspace => Key::Backspace,
-                        event::KeyCode::Esc => Key::Esc,
-                        _ => return None,
-                    };
-
-                    // let modifier = if modifiers == event::KeyModifiers::ALT {
-                    //     Some(KeyModifier::Alt)
-                    // } else if modifiers == event::KeyModifiers::CONTROL {
-                    //     Some(KeyModifier::Control)
-                    // } else if modifiers == event::KeyModifiers::SHIFT {
-                    //     Some(KeyModifier::Shift)
-                    // } else {
-                    //     None
-                    // };
-
-                    Event::Key(key)
-                }
-                event::Event::Resize(width, height) => {
-                    self.size = Size { width, height };
-                    Event::Resize
-                }
-            };
-            Some(event)
-        } else {
-            None
+        let crossterm_event = match event::read() {
+            Ok(crossterm_event) => crossterm_event,
+            Err(_) => {
+                self.input_closed = true;
+                return None;
+            }
+        };
+        let event = self.translate_event(crossterm_event);
+        self.record_event(event)
+    }
+
+    /// Reads an event as crossterm reports it, including events that [`read_event`](Self::read_event) doesn't report,
+    /// such as media keys. Like `read_event`, it sets the new size if the terminal has been resized.
+    ///
+    /// Note that this ties the caller to crossterm's types, which change between versions of crossterm,
+    /// and that it's only available with the `crossterm` backend.
+    /// The settings for which events are reported, such as [`set_report_key_releases`](Self::set_report_key_releases),
+    /// are not applied.
+    pub fn read_raw_event(&mut self) -> Option<event::Event> {
+        let crossterm_event = event::read().ok()?;
+        Some(self.raw_event(crossterm_event))
+    }
+
+    /// Sets the new size if `crossterm_event` is a resize and returns it as it is.
+    fn raw_event(&mut self, crossterm_event: event::Event) -> event::Event {
+        if let event::Event::Resize(width, height) = crossterm_event {
+            self.size = Size { width, height };
         }
+        crossterm_event
+    }
+
+    /// Translates an event of crossterm into an event of this crate.
+    fn translate_event(&mut self, crossterm_event: event::Event) -> Option<Event> {
+        let old_size = self.size;
+        let crossterm_event = self.raw_event(crossterm_event);
+        let event = match translate_event(&crossterm_event, old_size) {
+            Some(event) => event,
+            // Only describe the event if it's going to be reported
+            None if self.report_unknown_events => Event::Unknown(format!("{:?}", crossterm_event)),
+            None => return None,
+        };
+        Some(event).filter(|event| self.is_reported(event))
     }
 
     pub fn poll_event(&mut self, timeout: Duration) -> Option<Event> {
-        if let Ok(true) = crossterm::event::poll(timeout) {
-            self.read_event()
-        } else {
-            None
+        match crossterm::event::poll(timeout) {
+            Ok(true) => self.read_event(),
+            Ok(false) => None,
+            Err(_) => {
+                self.input_closed = true;
+                None
+            }
         }
     }
 
+    /// Queries the primary device attributes (DA1) of the terminal, which tell what it supports,
+    /// e.g. 4 for sixel graphics.
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// With crossterm, this always returns `None`.
+    pub fn device_attributes(&mut self) -> Option<Vec<u16>> {
+        None
+    }
+
+    /// Queries the secondary device attributes (DA2) of the terminal, which are its type, its version
+    /// and its hardware options. See [`terminal_type_name`](crate::util::terminal_type_name) for the types.
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// With crossterm, this always returns `None`.
+    pub fn terminal_identity(&mut self) -> Option<(u16, u16, u16)> {
+        None
+    }
+
+    /// Asks the terminal for the size of its text area in pixels (XTWINOPS).
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// With crossterm, this always returns `None`.
+    pub fn report_text_area_pixels(&mut self) -> Option<Size> {
+        None
+    }
+
+    /// Asks the terminal for the size of its text area in cells (XTWINOPS).
+    ///
+    /// Note that this is only supported by the `minimal-unix` backend.
+    /// With crossterm, this always returns `None`.
+    pub fn report_text_area_cells(&mut self) -> Option<Size> {
+        None
+    }
+
     /// Sets the cursor to the top left corner.
     #[cfg(not(target_os = "windows"))]
     pub fn reset_cursor(&mut self) {
-        self.write("\u{1b}[;H");
+        self.write_escape("\u{1b}[;H");
+        self.cursor = Some(Point::default());
     }
 
     /// Sets the cursor to the top left corner.
@@ -148,56 +192,85 @@ impl<'a> Terminal<'a> {
     ///
     /// If possible, try to use the `move_cursor_{}_by` and `move_cursor_{}` methods instead for single operations.
     pub fn set_cursor(&mut self, point: Point) {
-        self.stdout.queue(cursor::MoveTo(point.x, point.y)).unwrap();
+        self.queue_command(cursor::MoveTo(point.x, point.y));
+        self.cursor = Some(point);
     }
 
     /// Sets the cursor X-coordinate to `x`.
     pub fn set_cursor_x(&mut self, x: u16) {
-        self.stdout.queue(cursor::MoveToColumn(x)).unwrap();
+        self.queue_command(cursor::MoveToColumn(x));
+        if let Some(cursor) = &mut self.cursor {
+            cursor.x = x;
+        }
     }
 
     /// Sets the cursor Y-coordinate to `y`.
     pub fn set_cursor_y(&mut self, y: u16) {
-        self.stdout.queue(cursor::MoveToRow(y)).unwrap();
+        self.queue_command(cursor::MoveToRow(y));
+        if let Some(cursor) = &mut self.cursor {
+            cursor.y = y;
+        }
     }
 
+    /// Moves the cursor up by `cells`, but not past the top of the terminal.
     pub fn move_cursor_up_by(&mut self, cells: u16) {
-        self.stdout.queue(cursor::MoveUp(cells)).unwrap();
+        let cells = self.clamp_cursor_move(Direction::Up, cells);
+        if cells != 0 {
+            self.queue_command(cursor::MoveUp(cells));
+        }
     }
+    /// Moves the cursor down by `cells`, but not past the bottom of the terminal.
     pub fn move_cursor_down_by(&mut self, cells: u16) {
-        self.stdout.queue(cursor::MoveDown(cells)).unwrap();
+        let cells = self.clamp_cursor_move(Direction::Down, cells);
+        if cells != 0 {
+            self.queue_command(cursor::MoveDown(cells));
+        }
     }
+    /// Moves the cursor left by `cells`, but not past the left edge of the terminal.
     pub fn move_cursor_left_by(&mut self, cells: u16) {
-        self.stdout.queue(cursor::MoveLeft(cells)).unwrap();
+        let cells = self.clamp_cursor_move(Direction::Left, cells);
+        if cells != 0 {
+            self.queue_command(cursor::MoveLeft(cells));
+        }
     }
+    /// Moves the cursor right by `cells`, but not past the right edge of the terminal.
     pub fn move_cursor_right_by(&mut self, cells: u16) {
-        self.stdout.queue(cursor::MoveRight(cells)).unwrap();
+        let cells = self.clamp_cursor_move(Direction::Right, cells);
+        if cells != 0 {
+            self.queue_command(cursor::MoveRight(cells));
+        }
     }
 
     #[cfg(not(target_os = "windows"))]
     pub fn move_cursor_up(&mut self) {
-        self.write("\u{1b}[A");
+        self.write_escape("\u{1b}[A");
+        self.clamp_cursor_move(Direction::Up, 1);
     }
     #[cfg(not(target_os = "windows"))]
     pub fn move_cursor_down(&mut self) {
-        self.write("\u{1b}[B");
+        self.write_escape("\u{1b}[B");
+        self.clamp_cursor_move(Direction::Down, 1);
     }
     #[cfg(not(target_os = "windows"))]
     pub fn move_cursor_left(&mut self) {
-        self.write("\u{1b}[D");
+        self.write_escape("\u{1b}[D");
+        self.clamp_cursor_move(Direction::Left, 1);
     }
     #[cfg(not(target_os = "windows"))]
     pub fn move_cursor_right(&mut self) {
-        self.write("\u{1b}[C");
+        self.write_escape("\u{1b}[C");
+        self.clamp_cursor_move(Direction::Right, 1);
     }
 
     #[cfg(not(target_os = "windows"))]
     pub fn next_line(&mut self) {
-        self.write("\u{1b}[E");
+        self.write_escape("\u{1b}[E");
+        self.track_line_move(Direction::Down);
     }
     #[cfg(not(target_os = "windows"))]
     pub fn previous_line(&mut self) {
-        self.write("\u{1b}[F");
+        self.write_escape("\u{1b}[F");
+        self.track_line_move(Direction::Up);
     }
 
     #[cfg(target_os = "windows")]
@@ -219,88 +292,98 @@ impl<'a> Terminal<'a> {
 
     #[cfg(target_os = "windows")]
     pub fn next_line(&mut self) {
-        self.stdout.queue(cursor::MoveToNextLine(1)).unwrap();
+        self.queue_command(cursor::MoveToNextLine(1));
+        self.track_line_move(Direction::Down);
     }
     #[cfg(target_os = "windows")]
     pub fn previous_line(&mut self) {
-        self.stdout.queue(cursor::MoveToPreviousLine(1)).unwrap();
+        self.queue_command(cursor::MoveToPreviousLine(1));
+        self.track_line_move(Direction::Up);
     }
 
     pub fn save_cursor_point(&mut self) {
-        self.stdout.queue(cursor::SavePosition).unwrap();
+        self.queue_command(cursor::SavePosition);
+        self.saved_cursor = self.cursor;
     }
     pub fn restore_cursor_point(&mut self) {
-        self.stdout.queue(cursor::RestorePosition).unwrap();
-    }
-
-    pub fn set_foreground_color(&mut self, color: Color) {
-        self.stdout
-            .queue(style::SetForegroundColor(Self::convert_color(color)))
-            .unwrap();
-    }
-    pub fn set_background_color(&mut self, color: Color) {
-        self.stdout
-            .queue(style::SetBackgroundColor(Self::convert_color(color)))
-            .unwrap();
-    }
-
-    //
-    // TODO for the following methods: Do they work on Windows?
-    //
-
-    // Reference: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands
-    // NOTE: clipboard functionality can be added: https://github.com/alacritty/alacritty/blob/3e867a056018c507d79396cb5c5b4b8309c609c2/alacritty_terminal/src/ansi.rs#L440
-
-    /// Changes the terminal's foreground text color to `hex_color`.
-    ///
-    /// `hex_color` must be a hexadecimal color such as `"FF0000"`.
-    pub fn change_foreground_color(&mut self, hex_color: &str) {
-        self.write(&format!("\u{1b}]10;#{}\u{7}", hex_color));
-    }
-    pub fn reset_foreground_color(&mut self) {
-        self.write("\u{1b}]110\u{7}");
+        self.queue_command(cursor::RestorePosition);
+        self.cursor = self.saved_cursor;
     }
 
-    /// Changes the terminal's background text color to `hex_color`.
+    /// Sets the foreground color of the following text.
     ///
-    /// `hex_color` must be a hexadecimal color such as `FF0000`.
-    pub fn change_background_color(&mut self, hex_color: &str) {
-        self.write(&format!("\u{1b}]11;#{}\u{7}", hex_color));
-    }
-    pub fn reset_background_color(&mut self) {
-        self.write("\u{1b}]111\u{7}");
+    /// Nothing is written if the color is already set or if colors are disabled.
+    pub fn set_foreground_color(&mut self, color: Color) {
+        if self.colors_enabled() && self.foreground_color != Some(color) {
+            if self.capabilities.color_depth <= ColorDepth::Ansi16 {
+                // crossterm sets 4-bit colors as 8-bit colors
+                self.write_escape(&format!(
+                    "\u{1b}[{}m",
+                    color.sgr(38, self.capabilities.color_depth)
+                ));
+            } else {
+                let color = Self::convert_color(color.to_depth(self.capabilities.color_depth));
+                self.queue_command(style::SetForegroundColor(color));
+            }
+            self.foreground_color = Some(color);
+        }
     }
-
-    /// Changes the terminal's cursor color to `hex_color`.
+    /// Sets the background color of the following text.
     ///
-    /// `hex_color` must be a hexadecimal color such as `FF0000`.
-    pub fn change_cursor_color(&mut self, hex_color: &str) {
-        self.write(&format!("\u{1b}]12;#{}\u{7}", hex_color));
-    }
-    pub fn reset_cursor_color(&mut self) {
-        self.write("\u{1b}]112\u{7}");
+    /// Nothing is written if the color is already set or if colors are disabled.
+    pub fn set_background_color(&mut self, color: Color) {
+        if self.colors_enabled() && self.background_color != Some(color) {
+            if self.capabilities.color_depth <= ColorDepth::Ansi16 {
+                // crossterm sets 4-bit colors as 8-bit colors
+                self.write_escape(&format!(
+                    "\u{1b}[{}m",
+                    color.sgr(48, self.capabilities.color_depth)
+                ));
+            } else {
+                let color = Self::convert_color(color.to_depth(self.capabilities.color_depth));
+                self.queue_command(style::SetBackgroundColor(color));
+            }
+            self.background_color = Some(color);
+        }
     }
 
     pub fn enable_italic(&mut self) {
-        self.write(&format!("{}", style::Attribute::Italic));
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape(&format!("{}", style::Attribute::Italic));
+        self.attributes.insert(Attribute::Italic);
     }
     pub fn disable_italic(&mut self) {
-        self.write(&format!("{}", style::Attribute::NoItalic));
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape(&format!("{}", style::Attribute::NoItalic));
+        self.attributes.remove(Attribute::Italic);
     }
 
+    /// Resets the colors and attributes.
+    ///
+    /// Note that this resets the attributes, such as bold, as well. To only reset a color, use
+    /// [`reset_foreground_color_only`](Self::reset_foreground_color_only) or
+    /// [`reset_background_color_only`](Self::reset_background_color_only).
     pub fn reset_colors(&mut self) {
-        self.stdout.queue(style::ResetColor).unwrap();
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.queue_command(style::ResetColor);
+        self.foreground_color = None;
+        self.background_color = None;
+        self.attributes = Attributes::empty();
     }
 
     pub fn clear(&mut self) {
-        self.stdout
-            .queue(terminal::Clear(terminal::ClearType::All))
-            .unwrap();
+        self.queue_command(terminal::Clear(terminal::ClearType::All));
+        self.buffer.invalidate();
     }
     pub fn clear_from_cursor_to_end(&mut self) {
-        self.stdout
-            .queue(terminal::Clear(terminal::ClearType::FromCursorUp))
-            .unwrap();
+        self.queue_command(terminal::Clear(terminal::ClearType::FromCursorUp));
+        self.buffer.invalidate();
     }
 
     fn convert_color(color: Color) -> style::Color {
@@ -326,11 +409,22 @@ impl<'a> Terminal<'a> {
         }
     }
 
-    pub(crate) fn size() -> Size {
-        let size = terminal::size().unwrap();
-        Size {
-            width: size.0,
-            height: size.1,
+    /// Queries the size of the terminal.
+    ///
+    /// Prefer [`size`](Self::size), which returns the size that is kept up to date by [`read_event`](Self::read_event).
+    pub fn query_size() -> io::Result<Size> {
+        let (width, height) = terminal::size()?;
+        Ok(Size { width, height })
+    }
+
+    /// Queues `command` to be written, unless escape sequences are suppressed.
+    ///
+    /// The cursor position is kept track of by the caller.
+    fn queue_command(&mut self, command: impl crossterm::Command) {
+        if self.capabilities.escapes {
+            let cursor = self.cursor;
+            self.queue(command).unwrap();
+            self.cursor = cursor;
         }
     }
 
@@ -338,3 +432,283 @@ impl<'a> Terminal<'a> {
         stdout.is_tty()
     }
 }
+
+/// Translates an event of crossterm into an event of this crate, if there is one.
+///
+/// `old_size` is the size of the terminal before the event, in case it's a resize.
+fn translate_event(crossterm_event: &event::Event, old_size: Size) -> Option<Event> {
+    Some(match crossterm_event {
+        event::Event::Mouse(event) => {
+            fn translate_button(button: event::MouseButton) -> MouseButton {
+                match button {
+                    event::MouseButton::Left => MouseButton::Left,
+                    event::MouseButton::Middle => MouseButton::Middle,
+                    event::MouseButton::Right => MouseButton::Right,
+                }
+            }
+
+            let kind = match event.kind {
+                event::MouseEventKind::Moved => MouseEventKind::Move,
+                event::MouseEventKind::Drag(button) => {
+                    MouseEventKind::Drag(translate_button(button))
+                }
+                event::MouseEventKind::Down(button) => {
+                    MouseEventKind::Press(translate_button(button))
+                }
+                event::MouseEventKind::Up(button) => {
+                    MouseEventKind::Release(translate_button(button))
+                }
+                event::MouseEventKind::ScrollUp => MouseEventKind::ScrollUp,
+                event::MouseEventKind::ScrollDown => MouseEventKind::ScrollDown,
+                event::MouseEventKind::ScrollLeft | event::MouseEventKind::ScrollRight => {
+                    return None
+                }
+            };
+
+            let point = Point {
+                x: event.column,
+                y: event.row,
+            };
+
+            Event::Mouse(MouseEvent {
+                kind,
+                point,
+                pixel: None,
+            })
+        }
+        event::Event::Key(event::KeyEvent {
+            code,
+            modifiers: crossterm_modifiers,
+            kind,
+            state: _,
+        }) => {
+            let kind = match kind {
+                event::KeyEventKind::Press => KeyEventKind::Press,
+                event::KeyEventKind::Repeat => KeyEventKind::Repeat,
+                event::KeyEventKind::Release => KeyEventKind::Release,
+            };
+
+            let key = match code {
+                event::KeyCode::Char(char) => Key::Char(*char),
+                event::KeyCode::Up => Key::Up,
+                event::KeyCode::Down => Key::Down,
+                event::KeyCode::Left => Key::Left,
+                event::KeyCode::Right => Key::Right,
+                event::KeyCode::Tab => Key::Tab,
+                event::KeyCode::BackTab => Key::BackTab,
+                event::KeyCode::Enter => Key::Enter,
+                event::KeyCode::F(number) => Key::F(*number),
+                event::KeyCode::Backspace => Key::Backspace,
+                event::KeyCode::Esc => Key::Esc,
+                _ => return None,
+            };
+
+            let mut modifiers = KeyModifiers::empty();
+            for (crossterm_modifier, modifier) in [
+                (event::KeyModifiers::SHIFT, KeyModifier::Shift),
+                (event::KeyModifiers::ALT, KeyModifier::Alt),
+                (event::KeyModifiers::CONTROL, KeyModifier::Control),
+            ] {
+                if crossterm_modifiers.contains(crossterm_modifier) {
+                    modifiers.insert(modifier);
+                }
+            }
+            // BackTab always comes with Shift, like the other backends and `KeyEvent::parse` report it
+            if key == Key::BackTab {
+                modifiers.insert(KeyModifier::Shift);
+            }
+
+            Event::Key(KeyEvent {
+                key,
+                kind,
+                modifiers,
+            })
+        }
+        event::Event::Resize(width, height) => Event::Resize {
+            old: old_size,
+            new: Size {
+                width: *width,
+                height: *height,
+            },
+        },
+        event::Event::FocusGained | event::Event::FocusLost | event::Event::Paste(_) => {
+            return None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal() -> Terminal<'static, Vec<u8>> {
+        Terminal::test(Size {
+            width: 80,
+            height: 24,
+        })
+    }
+
+    fn key_event(code: event::KeyCode, kind: event::KeyEventKind) -> event::Event {
+        event::Event::Key(event::KeyEvent::new_with_kind(
+            code,
+            event::KeyModifiers::NONE,
+            kind,
+        ))
+    }
+
+    #[test]
+    fn test_translate_resize() {
+        let mut terminal = terminal();
+        assert_eq!(
+            terminal.translate_event(event::Event::Resize(100, 50)),
+            Some(Event::Resize {
+                old: Size {
+                    width: 80,
+                    height: 24
+                },
+                new: Size {
+                    width: 100,
+                    height: 50
+                }
+            })
+        );
+        assert_eq!(
+            terminal.size(),
+            Size {
+                width: 100,
+                height: 50
+            }
+        );
+    }
+
+    #[test]
+    fn test_translate_key_event_kind() {
+        let mut terminal = terminal();
+        let press = key_event(event::KeyCode::Char('a'), event::KeyEventKind::Press);
+        let repeat = key_event(event::KeyCode::Char('a'), event::KeyEventKind::Repeat);
+        let release = key_event(event::KeyCode::Char('a'), event::KeyEventKind::Release);
+
+        let events = vec![press, release.clone()]
+            .into_iter()
+            .filter_map(|event| terminal.translate_event(event))
+            .collect::<Vec<_>>();
+        assert_eq!(events, [Event::Key(Key::Char('a').into())]);
+
+        terminal.set_report_key_repeats(true);
+        terminal.set_report_key_releases(true);
+        assert_eq!(
+            terminal.translate_event(repeat),
+            Some(Event::Key(KeyEvent {
+                key: Key::Char('a'),
+                kind: KeyEventKind::Repeat,
+                modifiers: KeyModifiers::empty(),
+            }))
+        );
+        assert_eq!(
+            terminal.translate_event(release),
+            Some(Event::Key(KeyEvent {
+                key: Key::Char('a'),
+                kind: KeyEventKind::Release,
+                modifiers: KeyModifiers::empty(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_translate_back_tab() {
+        let mut terminal = terminal();
+        assert_eq!(
+            terminal.translate_event(key_event(
+                event::KeyCode::BackTab,
+                event::KeyEventKind::Press
+            )),
+            Some(Event::Key(KeyEvent::parse("BackTab").unwrap()))
+        );
+        assert_eq!(
+            terminal.translate_event(key_event(event::KeyCode::Tab, event::KeyEventKind::Press)),
+            Some(Event::Key(Key::Tab.into()))
+        );
+        // crossterm reports Shift+Tab as BackTab with Shift
+        assert_eq!(
+            terminal.translate_event(event::Event::Key(event::KeyEvent::new(
+                event::KeyCode::BackTab,
+                event::KeyModifiers::SHIFT
+            ))),
+            Some(Event::Key(KeyEvent::parse("Shift+Tab").unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_translate_function_keys() {
+        let mut terminal = terminal();
+        assert_eq!(
+            terminal.translate_event(key_event(event::KeyCode::F(13), event::KeyEventKind::Press)),
+            Some(Event::Key(Key::F(13).into()))
+        );
+        assert_eq!(
+            terminal.translate_event(event::Event::Key(event::KeyEvent::new(
+                event::KeyCode::F(5),
+                event::KeyModifiers::CONTROL,
+            ))),
+            Some(Event::Key(KeyEvent {
+                key: Key::F(5),
+                kind: KeyEventKind::Press,
+                modifiers: KeyModifiers::from(&[KeyModifier::Control][..]),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_convert_bright_colors() {
+        let convert = Terminal::<Vec<u8>>::convert_color;
+        assert_eq!(convert(Color::BRIGHT_BLACK), style::Color::DarkGrey);
+        assert_eq!(convert(Color::BRIGHT_RED), style::Color::Red);
+        assert_eq!(convert(Color::BRIGHT_GREEN), style::Color::Green);
+        assert_eq!(convert(Color::BRIGHT_YELLOW), style::Color::Yellow);
+        assert_eq!(convert(Color::BRIGHT_BLUE), style::Color::Blue);
+        assert_eq!(convert(Color::BRIGHT_MAGENTA), style::Color::Magenta);
+        assert_eq!(convert(Color::BRIGHT_CYAN), style::Color::Cyan);
+        assert_eq!(convert(Color::BRIGHT_WHITE), style::Color::White);
+    }
+
+    #[test]
+    fn test_raw_event() {
+        let mut terminal = terminal();
+        let media_key = key_event(
+            event::KeyCode::Media(event::MediaKeyCode::Play),
+            event::KeyEventKind::Press,
+        );
+        assert_eq!(terminal.translate_event(media_key.clone()), None);
+        assert_eq!(terminal.raw_event(media_key.clone()), media_key);
+
+        let resize = event::Event::Resize(100, 50);
+        assert_eq!(terminal.raw_event(resize.clone()), resize);
+        assert_eq!(
+            terminal.size(),
+            Size {
+                width: 100,
+                height: 50
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_event() {
+        let mut terminal = terminal();
+        let media_key = key_event(
+            event::KeyCode::Media(event::MediaKeyCode::Play),
+            event::KeyEventKind::Press,
+        );
+        assert_eq!(terminal.translate_event(media_key.clone()), None);
+
+        terminal.set_report_unknown_events(true);
+        match terminal.translate_event(media_key) {
+            Some(Event::Unknown(description)) => assert!(description.contains("Play")),
+            event => panic!("expected an unknown event but got {:?}", event),
+        }
+        assert_eq!(
+            terminal.translate_event(event::Event::FocusGained),
+            Some(Event::Unknown("FocusGained".to_string()))
+        );
+    }
+}
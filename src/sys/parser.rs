@@ -0,0 +1,783 @@
+//! Decoding of terminal input into events, for backends that read the input themselves.
+
+use crate::{
+    event::{
+        Event, Key, KeyEvent, KeyEventKind, KeyModifier, KeyModifiers, KeypadKey, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
+    util::{Point, Size},
+};
+use std::collections::VecDeque;
+
+const ESCAPE: u8 = 0x1b;
+
+/// Decodes terminal input into events. Input that ends in the middle of a sequence is kept until the rest arrives.
+///
+/// Replies to queries are kept separately from the events.
+#[derive(Debug, Default)]
+pub(crate) struct Parser {
+    buffer: Vec<u8>,
+    /// Events that have been decoded while looking for replies.
+    events: VecDeque<Event>,
+    replies: Vec<Reply>,
+    /// The size of a cell in pixels if mouse positions are reported in pixels.
+    pub(crate) mouse_pixels: Option<Size>,
+    /// Whether the byte 0x08 is decoded as Ctrl+H rather than Backspace.
+    pub(crate) ctrl_h: bool,
+}
+
+/// A reply of the terminal to a query.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Reply {
+    /// The primary device attributes (DA1), as replied to `ESC [ c`.
+    DeviceAttributes(Vec<u16>),
+    /// The terminal's type, version and hardware options (DA2), as replied to `ESC [ > c`.
+    TerminalIdentity(u16, u16, u16),
+    /// The size of the text area in pixels, as replied to `ESC [ 14 t`.
+    TextAreaPixels(Size),
+    /// The size of the text area in cells, as replied to `ESC [ 18 t`.
+    TextAreaCells(Size),
+}
+
+/// The result of decoding the start of some input.
+#[derive(Debug)]
+enum Parsed {
+    /// An event and the amount of bytes it was decoded from.
+    Event(Event, usize),
+    /// A reply and the amount of bytes it was decoded from.
+    Reply(Reply, usize),
+    /// An amount of bytes that don't map to an event.
+    Skip(usize),
+    /// The input ends in the middle of a sequence.
+    Incomplete,
+}
+
+impl Parser {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            events: VecDeque::new(),
+            replies: Vec::new(),
+            mouse_pixels: None,
+            ctrl_h: false,
+        }
+    }
+
+    /// Adds input to be decoded.
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Decodes the next event.
+    ///
+    /// If `more` is `true`, more input is expected to follow and a sequence that is incomplete is kept until it does.
+    /// Otherwise it is decoded as far as possible, e.g. a lone escape as [`Key::Esc`].
+    /// Input that doesn't map to any other event is decoded as [`Event::Unknown`].
+    pub(crate) fn next(&mut self, more: bool) -> Option<Event> {
+        self.events.pop_front().or_else(|| self.decode(more))
+    }
+
+    /// Decodes all input and returns the first reply for which `f` returns `Some`, if any.
+    ///
+    /// Events decoded in the meantime are kept to be returned by [`next`](Self::next).
+    pub(crate) fn take_reply<T>(&mut self, f: impl Fn(&Reply) -> Option<T>) -> Option<T> {
+        while let Some(event) = self.decode(true) {
+            self.events.push_back(event);
+        }
+        let (index, value) = self
+            .replies
+            .iter()
+            .enumerate()
+            .find_map(|(index, reply)| Some((index, f(reply)?)))?;
+        self.replies.remove(index);
+        Some(value)
+    }
+
+    /// Decodes the next event from the input, keeping the replies decoded in the meantime.
+    fn decode(&mut self, more: bool) -> Option<Event> {
+        while !self.buffer.is_empty() {
+            match parse(&self.buffer, more, self.mouse_pixels, self.ctrl_h) {
+                Parsed::Event(event, length) => {
+                    self.buffer.drain(..length);
+                    return Some(event);
+                }
+                Parsed::Reply(reply, length) => {
+                    self.buffer.drain(..length);
+                    self.replies.push(reply);
+                }
+                Parsed::Skip(length) => {
+                    let bytes = self.buffer.drain(..length);
+                    return Some(Event::Unknown(bytes.as_slice().escape_ascii().to_string()));
+                }
+                Parsed::Incomplete => return None,
+            }
+        }
+        None
+    }
+}
+
+fn key(key: Key, length: usize) -> Parsed {
+    Parsed::Event(Event::Key(key.into()), length)
+}
+
+/// Decodes the start of `bytes`.
+///
+/// Control characters are decoded as Ctrl and the letter at their position in the alphabet, e.g. 0x01 as Ctrl+A,
+/// except for those that the named keys send: Tab (Ctrl+I), Enter (Ctrl+J and Ctrl+M) and,
+/// unless `ctrl_h` is `true`, Backspace (Ctrl+H).
+fn parse(bytes: &[u8], more: bool, mouse_pixels: Option<Size>, ctrl_h: bool) -> Parsed {
+    match bytes[0] {
+        ESCAPE => match bytes.get(1) {
+            None if more => Parsed::Incomplete,
+            None | Some(&ESCAPE) => key(Key::Esc, 1),
+            Some(b'[') => parse_csi(bytes, more, mouse_pixels),
+            Some(b'O') => parse_ss3(bytes, more),
+            // Alt and a key
+            Some(_) => match parse(&bytes[1..], more, mouse_pixels, ctrl_h) {
+                Parsed::Event(Event::Key(mut event), length) => {
+                    event.modifiers.insert(KeyModifier::Alt);
+                    Parsed::Event(Event::Key(event), 1 + length)
+                }
+                Parsed::Event(event, length) => Parsed::Event(event, 1 + length),
+                Parsed::Reply(reply, length) => Parsed::Reply(reply, 1 + length),
+                Parsed::Skip(length) => Parsed::Skip(1 + length),
+                Parsed::Incomplete => Parsed::Incomplete,
+            },
+        },
+        b'\r' | b'\n' => key(Key::Enter, 1),
+        b'\t' => key(Key::Tab, 1),
+        0x7f => key(Key::Backspace, 1),
+        0x08 if !ctrl_h => key(Key::Backspace, 1),
+        // Control and a letter
+        byte @ 0x01..=0x1a => {
+            let event = KeyEvent {
+                modifiers: KeyModifiers::from(&[KeyModifier::Control][..]),
+                ..Key::Char((b'a' + byte - 1) as char).into()
+            };
+            Parsed::Event(Event::Key(event), 1)
+        }
+        byte if byte < b' ' => Parsed::Skip(1),
+        _ => parse_char(bytes),
+    }
+}
+
+/// Parses a UTF-8 encoded character.
+///
+/// A character that is incomplete is always kept until the rest arrives because its bytes may be read separately.
+/// Invalid bytes are decoded as a single [`char::REPLACEMENT_CHARACTER`].
+fn parse_char(bytes: &[u8]) -> Parsed {
+    let bytes = &bytes[..bytes.len().min(4)];
+    let string = match std::str::from_utf8(bytes) {
+        Ok(string) => string,
+        Err(error) if error.valid_up_to() > 0 => {
+            std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap()
+        }
+        Err(error) => {
+            return match error.error_len() {
+                Some(length) => key(Key::Char(char::REPLACEMENT_CHARACTER), length),
+                None => Parsed::Incomplete,
+            }
+        }
+    };
+    let char = string.chars().next().unwrap();
+    key(Key::Char(char), char.len_utf8())
+}
+
+/// Parses a sequence starting with `ESC O`.
+fn parse_ss3(bytes: &[u8], more: bool) -> Parsed {
+    match bytes.get(2) {
+        None if more => Parsed::Incomplete,
+        // Alt and O
+        None => Parsed::Event(
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::from(&[KeyModifier::Alt][..]),
+                ..Key::Char('O').into()
+            }),
+            2,
+        ),
+        Some(b'A') => key(Key::Up, 3),
+        Some(b'B') => key(Key::Down, 3),
+        Some(b'C') => key(Key::Right, 3),
+        Some(b'D') => key(Key::Left, 3),
+        Some(byte @ b'P'..=b'S') => key(Key::F(1 + byte - b'P'), 3),
+        // Application keypad mode
+        Some(byte @ b'p'..=b'y') => key(Key::Keypad(KeypadKey::Digit(byte - b'p')), 3),
+        Some(b'M') => key(Key::Keypad(KeypadKey::Enter), 3),
+        Some(b'k') => key(Key::Keypad(KeypadKey::Add), 3),
+        Some(b'm') => key(Key::Keypad(KeypadKey::Subtract), 3),
+        Some(b'j') => key(Key::Keypad(KeypadKey::Multiply), 3),
+        Some(b'o') => key(Key::Keypad(KeypadKey::Divide), 3),
+        Some(b'n') => key(Key::Keypad(KeypadKey::Decimal), 3),
+        Some(_) => Parsed::Skip(3),
+    }
+}
+
+/// Parses a sequence starting with `ESC [`.
+fn parse_csi(bytes: &[u8], more: bool, mouse_pixels: Option<Size>) -> Parsed {
+    let end = match bytes[2..]
+        .iter()
+        .position(|byte| (0x40..=0x7e).contains(byte))
+    {
+        Some(index) => 2 + index,
+        None if more => return Parsed::Incomplete,
+        None => return Parsed::Skip(bytes.len()),
+    };
+    let length = end + 1;
+    let parameters = &bytes[2..end];
+
+    if let Some(parameters) = parameters.strip_prefix(b"<") {
+        return parse_sgr_mouse(parameters, bytes[end], length, mouse_pixels);
+    }
+    if let (Some(parameters), b'c') = (parameters.strip_prefix(b"?"), bytes[end]) {
+        let attributes = numbers(parameters).flatten().collect();
+        return Parsed::Reply(Reply::DeviceAttributes(attributes), length);
+    }
+    if let (Some(parameters), b'c') = (parameters.strip_prefix(b">"), bytes[end]) {
+        let mut numbers = numbers(parameters);
+        return match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Some(kind)), Some(Some(version)), hardware) => Parsed::Reply(
+                Reply::TerminalIdentity(kind, version, hardware.flatten().unwrap_or(0)),
+                length,
+            ),
+            _ => Parsed::Skip(length),
+        };
+    }
+    if bytes[end] == b't' {
+        let mut numbers = numbers(parameters);
+        return match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Some(kind @ (4 | 8))), Some(Some(height)), Some(Some(width))) => {
+                let size = Size { width, height };
+                let reply = if kind == 4 {
+                    Reply::TextAreaPixels(size)
+                } else {
+                    Reply::TextAreaCells(size)
+                };
+                Parsed::Reply(reply, length)
+            }
+            _ => Parsed::Skip(length),
+        };
+    }
+
+    let mut parameters = parameters.split(|byte| *byte == b';');
+    let number = parameters.next().and_then(number);
+    let modifiers = parameters.next();
+    // With the Kitty keyboard protocol, the modifiers may be followed by the event kind, e.g. `1:3`
+    let kind = modifiers.map_or(KeyEventKind::Press, kind);
+    let mut modifiers = modifiers
+        .and_then(self::number)
+        .map_or(KeyModifiers::empty(), self::modifiers);
+
+    let key = match bytes[end] {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        byte @ b'P'..=b'S' => Key::F(1 + byte - b'P'),
+        // Shift+Tab
+        b'Z' => {
+            modifiers.insert(KeyModifier::Shift);
+            Key::BackTab
+        }
+        b'~' => match number {
+            // xterm's modifyOtherKeys reports a key with modifiers as `ESC [ 27 ; modifiers ; code ~`
+            Some(27) => match parameters
+                .next()
+                .and_then(self::number)
+                .and_then(parse_kitty_key)
+            {
+                Some(key) => key,
+                None => return Parsed::Skip(length),
+            },
+            Some(number @ 11..=15) => Key::F(number as u8 - 10),
+            Some(number @ 17..=21) => Key::F(number as u8 - 11),
+            Some(number @ 23..=26) => Key::F(number as u8 - 12),
+            Some(number @ 28..=29) => Key::F(number as u8 - 13),
+            Some(number @ 31..=34) => Key::F(number as u8 - 14),
+            _ => return Parsed::Skip(length),
+        },
+        b'u' => match number.and_then(parse_kitty_key) {
+            Some(key) => key,
+            None => return Parsed::Skip(length),
+        },
+        _ => return Parsed::Skip(length),
+    };
+
+    Parsed::Event(
+        Event::Key(KeyEvent {
+            key,
+            kind,
+            modifiers,
+        }),
+        length,
+    )
+}
+
+/// Parses a key code of the Kitty keyboard protocol as reported in `ESC [ code u`,
+/// which is also how xterm's modifyOtherKeys reports keys.
+fn parse_kitty_key(code: u16) -> Option<Key> {
+    Some(match code {
+        13 => Key::Enter,
+        9 => Key::Tab,
+        27 => Key::Esc,
+        127 => Key::Backspace,
+        57399..=57408 => Key::Keypad(KeypadKey::Digit((code - 57399) as u8)),
+        57409 => Key::Keypad(KeypadKey::Decimal),
+        57410 => Key::Keypad(KeypadKey::Divide),
+        57411 => Key::Keypad(KeypadKey::Multiply),
+        57412 => Key::Keypad(KeypadKey::Subtract),
+        57413 => Key::Keypad(KeypadKey::Add),
+        57414 => Key::Keypad(KeypadKey::Enter),
+        57376..=57398 => Key::F((code - 57376 + 13) as u8),
+        // Other functional keys are in the Unicode Private Use Area
+        0xe000..=0xf8ff => return None,
+        code => Key::Char(char::from_u32(code as u32)?),
+    })
+}
+
+/// Parses the first sub-parameter of a control sequence parameter as a number.
+fn number(parameter: &[u8]) -> Option<u16> {
+    let sub_parameter = parameter.split(|byte| *byte == b':').next()?;
+    std::str::from_utf8(sub_parameter).ok()?.parse().ok()
+}
+
+/// Parses the numeric parameters of a control sequence. Parameters that aren't numbers are `None`.
+fn numbers(parameters: &[u8]) -> impl Iterator<Item = Option<u16>> + '_ {
+    parameters.split(|byte| *byte == b';').map(number)
+}
+
+/// Parses modifiers encoded as one more than a bit set of Shift (1), Alt (2) and Control (4).
+fn modifiers(number: u16) -> KeyModifiers {
+    let bits = number.saturating_sub(1);
+    let mut modifiers = KeyModifiers::empty();
+    for (index, modifier) in KeyModifier::ALL.iter().enumerate() {
+        if bits & 1 << index != 0 {
+            modifiers.insert(*modifier);
+        }
+    }
+    modifiers
+}
+
+/// Parses the event kind that the Kitty keyboard protocol reports as the second sub-parameter of the modifiers.
+fn kind(parameter: &[u8]) -> KeyEventKind {
+    match parameter.split(|byte| *byte == b':').nth(1) {
+        Some(b"2") => KeyEventKind::Repeat,
+        Some(b"3") => KeyEventKind::Release,
+        _ => KeyEventKind::Press,
+    }
+}
+
+/// Parses an SGR mouse report of the form `ESC [ < button ; x ; y M` for presses and `... m` for releases.
+///
+/// If `mouse_pixels` is the size of a cell in pixels, the position is in pixels.
+fn parse_sgr_mouse(
+    parameters: &[u8],
+    end: u8,
+    length: usize,
+    mouse_pixels: Option<Size>,
+) -> Parsed {
+    let mut numbers = numbers(parameters);
+    let (button, x, y) = match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Some(button)), Some(Some(x)), Some(Some(y))) if x > 0 && y > 0 => (button, x, y),
+        _ => return Parsed::Skip(length),
+    };
+
+    let mouse_button = match button & 0b11 {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    };
+
+    let kind = if button & 64 != 0 {
+        match button & 0b11 {
+            0 => MouseEventKind::ScrollUp,
+            1 => MouseEventKind::ScrollDown,
+            _ => return Parsed::Skip(length),
+        }
+    } else if button & 32 != 0 {
+        match mouse_button {
+            Some(mouse_button) => MouseEventKind::Drag(mouse_button),
+            None => MouseEventKind::Move,
+        }
+    } else {
+        match (mouse_button, end) {
+            (Some(mouse_button), b'M') => MouseEventKind::Press(mouse_button),
+            (Some(mouse_button), _) => MouseEventKind::Release(mouse_button),
+            (None, _) => return Parsed::Skip(length),
+        }
+    };
+
+    let position = Point { x: x - 1, y: y - 1 };
+    let (point, pixel) = match mouse_pixels {
+        Some(cell) => (
+            Point {
+                x: position.x.checked_div(cell.width).unwrap_or(0),
+                y: position.y.checked_div(cell.height).unwrap_or(0),
+            },
+            Some(position),
+        ),
+        None => (position, None),
+    };
+
+    Parsed::Event(Event::Mouse(MouseEvent { kind, point, pixel }), length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<Event> {
+        let mut parser = Parser::new();
+        parser.feed(bytes);
+        std::iter::from_fn(|| parser.next(false)).collect()
+    }
+
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+        parse(bytes)
+            .into_iter()
+            .map(|event| match event {
+                Event::Key(KeyEvent {
+                    key,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => key,
+                event => panic!("expected a key event but got {:?}", event),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(keys(b"a\xc3\xa4"), [Key::Char('a'), Key::Char('ä')]);
+        assert_eq!(
+            keys(b"\x1b[A\x1bOB\x1b[1;5C"),
+            [Key::Up, Key::Down, Key::Right]
+        );
+        assert_eq!(
+            keys(b"\x1bOP\x1b[15~\x1b[24~"),
+            [Key::F(1), Key::F(5), Key::F(12)]
+        );
+        assert_eq!(
+            keys(b"\r\t\x7f\x01"),
+            [Key::Enter, Key::Tab, Key::Backspace, Key::Char('a')]
+        );
+        assert_eq!(keys(b"\t\x1b[Z"), [Key::Tab, Key::BackTab]);
+        // Key bindings parsed from strings match what the terminal sends
+        for binding in ["Shift+Tab", "BackTab", "Shift+BackTab"] {
+            assert_eq!(
+                parse(b"\x1b[Z"),
+                [Event::Key(KeyEvent::parse(binding).unwrap())]
+            );
+        }
+        assert_eq!(keys(b"\x1b"), [Key::Esc]);
+        assert_eq!(keys(b"\x1bx"), [Key::Char('x')]);
+        // Alt+O isn't mistaken for the start of an SS3 sequence once no more input is pending
+        let alt = |char| {
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::from(&[KeyModifier::Alt][..]),
+                ..Key::Char(char).into()
+            })
+        };
+        assert_eq!(parse(b"\x1bO"), [alt('O')]);
+        assert_eq!(parse(b"\x1bo"), [alt('o')]);
+        assert_eq!(
+            parse(b"\x1b[2~a\x1c"),
+            [
+                Event::Unknown("\\x1b[2~".to_string()),
+                Event::Key(Key::Char('a').into()),
+                Event::Unknown("\\x1c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_control_characters() {
+        let ctrl = |char| {
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::from(&[KeyModifier::Control][..]),
+                ..Key::Char(char).into()
+            })
+        };
+
+        assert_eq!(
+            parse(b"\x08\x09\x0d\x01\x17"),
+            [
+                Event::Key(Key::Backspace.into()),
+                Event::Key(Key::Tab.into()),
+                Event::Key(Key::Enter.into()),
+                ctrl('a'),
+                ctrl('w'),
+            ]
+        );
+
+        let mut parser = Parser::new();
+        parser.ctrl_h = true;
+        parser.feed(b"\x08\x7f");
+        assert_eq!(parser.next(false), Some(ctrl('h')));
+        assert_eq!(parser.next(false), Some(Event::Key(Key::Backspace.into())));
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        let modifiers = |bytes| {
+            parse(bytes)
+                .into_iter()
+                .map(|event| match event {
+                    Event::Key(KeyEvent { key, modifiers, .. }) => {
+                        (key, modifiers.iter().collect::<Vec<_>>())
+                    }
+                    event => panic!("expected a key event but got {:?}", event),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            modifiers(b"\x1b[15;5~\x1b[1;2P\x1b[1;7A\x1b[57376u\x1b[57387;5u"),
+            [
+                (Key::F(5), vec![KeyModifier::Control]),
+                (Key::F(1), vec![KeyModifier::Shift]),
+                (Key::Up, vec![KeyModifier::Alt, KeyModifier::Control]),
+                (Key::F(13), vec![]),
+                (Key::F(24), vec![KeyModifier::Control]),
+            ]
+        );
+        assert_eq!(
+            modifiers(b"\x03\x1ba\x1b\x1b[B"),
+            [
+                (Key::Char('c'), vec![KeyModifier::Control]),
+                (Key::Char('a'), vec![KeyModifier::Alt]),
+                (Key::Esc, vec![]),
+                (Key::Down, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_keypad() {
+        assert_eq!(
+            keys(b"\x1bOp\x1bOy\x1bOM\x1bOk\x1bOm\x1bOj\x1bOo\x1bOn"),
+            [
+                Key::Keypad(KeypadKey::Digit(0)),
+                Key::Keypad(KeypadKey::Digit(9)),
+                Key::Keypad(KeypadKey::Enter),
+                Key::Keypad(KeypadKey::Add),
+                Key::Keypad(KeypadKey::Subtract),
+                Key::Keypad(KeypadKey::Multiply),
+                Key::Keypad(KeypadKey::Divide),
+                Key::Keypad(KeypadKey::Decimal),
+            ]
+        );
+        assert_eq!(
+            keys(b"\x1b[57404u\x1b[57414u\x1b[57413u\x1b[55u"),
+            [
+                Key::Keypad(KeypadKey::Digit(5)),
+                Key::Keypad(KeypadKey::Enter),
+                Key::Keypad(KeypadKey::Add),
+                Key::Char('7'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_key_event_kind() {
+        let kinds = |bytes| {
+            parse(bytes)
+                .into_iter()
+                .map(|event| match event {
+                    Event::Key(KeyEvent { key, kind, .. }) => (key, kind),
+                    event => panic!("expected a key event but got {:?}", event),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds(b"\x1b[97u\x1b[97;1:1u\x1b[97;1:2u\x1b[97;1:3u"),
+            [
+                (Key::Char('a'), KeyEventKind::Press),
+                (Key::Char('a'), KeyEventKind::Press),
+                (Key::Char('a'), KeyEventKind::Repeat),
+                (Key::Char('a'), KeyEventKind::Release),
+            ]
+        );
+        assert_eq!(
+            kinds(b"\x1b[1;1:3A\x1b[15;1:2~\x1b[57414;1:3u"),
+            [
+                (Key::Up, KeyEventKind::Release),
+                (Key::F(5), KeyEventKind::Repeat),
+                (Key::Keypad(KeypadKey::Enter), KeyEventKind::Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        let mut parser = Parser::new();
+        parser.feed(b"\x1b[1");
+        assert_eq!(parser.next(true), None);
+        parser.feed(b"5~");
+        assert_eq!(parser.next(true), Some(Event::Key(Key::F(5).into())));
+        assert_eq!(parser.next(true), None);
+    }
+
+    #[test]
+    fn test_parse_split_char() {
+        let mut parser = Parser::new();
+        for byte in &"世".as_bytes()[..2] {
+            parser.feed(&[*byte]);
+            assert_eq!(parser.next(false), None);
+        }
+        parser.feed(&"世".as_bytes()[2..]);
+        assert_eq!(parser.next(false), Some(Event::Key(Key::Char('世').into())));
+        assert_eq!(parser.next(false), None);
+
+        // Invalid bytes are reported once
+        assert_eq!(
+            keys(b"\xe4\xb8a\xff"),
+            [
+                Key::Char(char::REPLACEMENT_CHARACTER),
+                Key::Char('a'),
+                Key::Char(char::REPLACEMENT_CHARACTER)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_device_attributes() {
+        let device_attributes = |reply: &Reply| match reply {
+            Reply::DeviceAttributes(attributes) => Some(attributes.clone()),
+            _ => None,
+        };
+
+        let mut parser = Parser::new();
+        parser.feed(b"a\x1b[?64;1;2;4;6;9;15;16;17;18;21;22;28cb\x1b[?65;1;");
+        assert_eq!(
+            parser.take_reply(device_attributes),
+            Some(vec![64, 1, 2, 4, 6, 9, 15, 16, 17, 18, 21, 22, 28])
+        );
+        assert_eq!(parser.take_reply(device_attributes), None);
+        parser.feed(b"9c");
+        assert_eq!(parser.take_reply(device_attributes), Some(vec![65, 1, 9]));
+
+        // The input around the replies is kept
+        assert_eq!(parser.next(false), Some(Event::Key(Key::Char('a').into())));
+        assert_eq!(parser.next(false), Some(Event::Key(Key::Char('b').into())));
+        assert_eq!(parser.next(false), None);
+
+        parser.feed(b"\x1b[?1;2c");
+        assert_eq!(parser.next(false), None);
+        assert_eq!(parser.take_reply(device_attributes), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_parse_terminal_identity() {
+        let terminal_identity = |reply: &Reply| match reply {
+            Reply::TerminalIdentity(kind, version, hardware) => Some((*kind, *version, *hardware)),
+            _ => None,
+        };
+
+        let mut parser = Parser::new();
+        parser.feed(b"a\x1b[>0;277;0cb");
+        assert_eq!(parser.take_reply(terminal_identity), Some((0, 277, 0)));
+        assert_eq!(
+            std::iter::from_fn(|| parser.next(false)).collect::<Vec<_>>(),
+            [
+                Event::Key(Key::Char('a').into()),
+                Event::Key(Key::Char('b').into())
+            ]
+        );
+
+        parser.feed(b"\x1b[>1;4000;29c\x1b[>84;0c");
+        assert_eq!(parser.take_reply(terminal_identity), Some((1, 4000, 29)));
+        assert_eq!(parser.take_reply(terminal_identity), Some((84, 0, 0)));
+    }
+
+    #[test]
+    fn test_parse_text_area_size() {
+        let mut parser = Parser::new();
+        parser.feed(b"\x1b[8;24;80t\x1b[4;480;800t\x1b[9;1;2t");
+        assert_eq!(
+            parser.take_reply(|reply| match reply {
+                Reply::TextAreaPixels(size) => Some(*size),
+                _ => None,
+            }),
+            Some(Size {
+                width: 800,
+                height: 480
+            })
+        );
+        assert_eq!(
+            parser.take_reply(|reply| match reply {
+                Reply::TextAreaCells(size) => Some(*size),
+                _ => None,
+            }),
+            Some(Size {
+                width: 80,
+                height: 24
+            })
+        );
+        assert_eq!(
+            parser.next(false),
+            Some(Event::Unknown("\\x1b[9;1;2t".to_string()))
+        );
+        assert_eq!(parser.next(false), None);
+    }
+
+    #[test]
+    fn test_parse_modify_other_keys() {
+        let event = |key, modifiers: &[KeyModifier]| {
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::from(modifiers),
+                ..KeyEvent::from(key)
+            })
+        };
+
+        assert_eq!(
+            parse(b"\x1b[27;6;59~\x1b[27;5;13~\x1b[27;3;97~"),
+            [
+                event(Key::Char(';'), &[KeyModifier::Shift, KeyModifier::Control]),
+                event(Key::Enter, &[KeyModifier::Control]),
+                event(Key::Char('a'), &[KeyModifier::Alt]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mouse() {
+        let point = Point { x: 2, y: 3 };
+        assert_eq!(
+            parse(b"\x1b[<0;3;4M\x1b[<0;3;4m\x1b[<32;3;4M\x1b[<35;3;4M\x1b[<65;3;4M"),
+            [
+                MouseEventKind::Press(MouseButton::Left),
+                MouseEventKind::Release(MouseButton::Left),
+                MouseEventKind::Drag(MouseButton::Left),
+                MouseEventKind::Move,
+                MouseEventKind::ScrollDown,
+            ]
+            .iter()
+            .map(|&kind| Event::Mouse(MouseEvent {
+                kind,
+                point,
+                pixel: None
+            }))
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_mouse_pixels() {
+        let mut parser = Parser::new();
+        parser.mouse_pixels = Some(Size {
+            width: 10,
+            height: 20,
+        });
+        parser.feed(b"\x1b[<0;35;41M");
+        assert_eq!(
+            parser.next(false),
+            Some(Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Press(MouseButton::Left),
+                point: Point { x: 3, y: 2 },
+                pixel: Some(Point { x: 34, y: 40 }),
+            }))
+        );
+    }
+}
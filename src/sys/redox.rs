@@ -13,7 +13,7 @@ use std::io::{self, Write};
 use std::time::Duration;
 use termion::{event, input::TermRead, is_tty, raw::IntoRawMode, screen};
 
-impl<'a> Terminal<'a> {
+impl<'a, W: Write> Terminal<'a, W> {
     pub fn enter_alternate_dimension(&mut self) {
         write!(self.stdout, "{}", screen::ToAlternateScreen);
     }
@@ -82,8 +82,12 @@ impl<'a> Terminal<'a> {
                     _ => return None,
                 }),
                 event::Event(width, height) => {
+                    let old = self.size;
                     self.size = Size { width, height };
-                    Event::Resize
+                    Event::Resize {
+                        old,
+                        new: self.size,
+                    }
                 }
             }
             Some(event)
@@ -0,0 +1,585 @@
+//! Minimal terminal implementation for Unix-like operating systems, enabled by the `minimal-unix` feature.
+//!
+//! Instead of using crossterm, escape sequences are written directly and raw mode is toggled using termios.
+
+use super::parser::{Parser, Reply};
+use crate::{
+    cursor::Direction,
+    event::Event,
+    util::{Attribute, Attributes, Color, Point, Size},
+    Terminal,
+};
+use std::{
+    io::{self, Write},
+    mem,
+    os::unix::io::RawFd,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once,
+    },
+    time::{Duration, Instant},
+};
+
+/// The terminal mode from before raw mode was enabled.
+static ORIGINAL_MODE: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+/// Standard input can only be read by one reader so the input that hasn't been decoded yet is global as well.
+static INPUT: Mutex<Parser> = Mutex::new(Parser::new());
+
+/// How long to wait for the terminal to reply to a query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Whether the terminal has been resized since this has last been checked.
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+impl<'a, W: Write> Terminal<'a, W> {
+    pub fn enter_alternate_dimension(&mut self) {
+        self.write_escape("\u{1b}[?1049h");
+        self.cursor = None;
+    }
+    pub fn exit_alternate_dimension(&mut self) {
+        self.write_escape("\u{1b}[?1049l");
+        self.cursor = None;
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.write_escape_fmt(format_args!("\u{1b}]0;{}\u{7}", title));
+    }
+
+    pub fn enable_raw_mode(&mut self) {
+        if self.tty {
+            enable_raw_mode(libc::STDIN_FILENO).unwrap();
+        }
+        self.raw_mode = true;
+    }
+    pub fn disable_raw_mode(&mut self) {
+        if self.tty {
+            disable_raw_mode(libc::STDIN_FILENO).unwrap();
+        }
+        self.raw_mode = false;
+    }
+
+    /// Enables mouse capture so that mouse events are reported. Does nothing if it's already enabled.
+    ///
+    /// See also [`capture_mouse`](Self::capture_mouse).
+    pub fn enable_mouse_capture(&mut self) {
+        if self.with_mouse {
+            return;
+        }
+        self.write_escape("\u{1b}[?1000h\u{1b}[?1002h\u{1b}[?1003h\u{1b}[?1015h\u{1b}[?1006h");
+        self.with_mouse = true;
+    }
+    /// Disables mouse capture. Does nothing if it's already disabled.
+    pub fn disable_mouse_capture(&mut self) {
+        if !self.with_mouse {
+            return;
+        }
+        self.write_escape("\u{1b}[?1006l\u{1b}[?1015l\u{1b}[?1003l\u{1b}[?1002l\u{1b}[?1000l");
+        self.with_mouse = false;
+    }
+
+    /// Shows the cursor. Does nothing if it's already visible.
+    pub fn show_cursor(&mut self) {
+        if !self.cursor_visible && self.capabilities.escapes {
+            self.write_escape("\u{1b}[?25h");
+            self.cursor_visible = true;
+        }
+    }
+    /// Hides the cursor. Does nothing if it's already hidden.
+    pub fn hide_cursor(&mut self) {
+        if self.cursor_visible && self.capabilities.escapes {
+            self.write_escape("\u{1b}[?25l");
+            self.cursor_visible = false;
+        }
+    }
+
+    /// Reads an event. It also sets the new size if the terminal has been resized, hence a mutable borrow of `self` is required.
+    pub fn read_event(&mut self) -> Option<Event> {
+        let event = self.next_event(None);
+        self.record_event(event)
+    }
+
+    pub fn poll_event(&mut self, timeout: Duration) -> Option<Event> {
+        let event = self.next_event(Some(timeout));
+        self.record_event(event)
+    }
+
+    /// Returns the next event, waiting at most `timeout` for input, or forever if it's `None`.
+    fn next_event(&mut self, timeout: Option<Duration>) -> Option<Event> {
+        static RESIZE_HANDLER: Once = Once::new();
+        RESIZE_HANDLER.call_once(handle_resizes);
+
+        if RESIZED.swap(false, Ordering::Relaxed) {
+            return Some(self.resize());
+        }
+
+        let mut input = INPUT.lock().unwrap();
+        input.mouse_pixels = if self.mouse_pixels {
+            Some(cell_pixel_size())
+        } else {
+            None
+        };
+        input.ctrl_h = self.decode_ctrl_h;
+
+        if let Some(event) = input.next(false) {
+            return Some(event).filter(|event| self.is_reported(event));
+        }
+        if self.input_closed {
+            return None;
+        }
+
+        if wait_for_input(libc::STDIN_FILENO, timeout) {
+            let mut buffer = [0; 1024];
+            let result = read(libc::STDIN_FILENO, &mut buffer);
+            // Nothing more can be read after the end of the input or an error other than a signal interrupting the read
+            self.input_closed = match &result {
+                Ok(count) => *count == 0,
+                Err(error) => error.kind() != io::ErrorKind::Interrupted,
+            };
+            let count = result.unwrap_or(0);
+            input.feed(&buffer[..count]);
+            return input
+                .next(count == buffer.len())
+                .filter(|event| self.is_reported(event));
+        }
+        drop(input);
+
+        if RESIZED.swap(false, Ordering::Relaxed) {
+            Some(self.resize())
+        } else {
+            None
+        }
+    }
+
+    /// Sets the new size after the terminal has been resized and returns the resize event.
+    fn resize(&mut self) -> Event {
+        let old = self.size;
+        self.update_size();
+        Event::Resize {
+            old,
+            new: self.size,
+        }
+    }
+
+    /// Sets the new size after the terminal has been resized.
+    ///
+    /// If the operating system doesn't know the size, the terminal is asked.
+    fn update_size(&mut self) {
+        self.size = match Self::query_size() {
+            Ok(size) if size != Size::default() => size,
+            _ => self.report_text_area_cells().unwrap_or(self.size),
+        };
+    }
+
+    /// Queries the primary device attributes (DA1) of the terminal, which tell what it supports,
+    /// e.g. 4 for sixel graphics.
+    ///
+    /// Returns `None` if the terminal doesn't reply in time. Input that arrives in the meantime is kept.
+    pub fn device_attributes(&mut self) -> Option<Vec<u16>> {
+        self.query("\u{1b}[c", |reply| match reply {
+            Reply::DeviceAttributes(attributes) => Some(attributes.clone()),
+            _ => None,
+        })
+    }
+
+    /// Queries the secondary device attributes (DA2) of the terminal, which are its type, its version
+    /// and its hardware options. See [`terminal_type_name`](crate::util::terminal_type_name) for the types.
+    ///
+    /// Returns `None` if the terminal doesn't reply in time. Input that arrives in the meantime is kept.
+    pub fn terminal_identity(&mut self) -> Option<(u16, u16, u16)> {
+        self.query("\u{1b}[>c", |reply| match reply {
+            Reply::TerminalIdentity(kind, version, hardware) => Some((*kind, *version, *hardware)),
+            _ => None,
+        })
+    }
+
+    /// Asks the terminal for the size of its text area in pixels (XTWINOPS).
+    ///
+    /// Unlike [`query_size`](Self::query_size), this works without the operating system knowing the size,
+    /// e.g. in remote sessions without a pseudoterminal.
+    /// Returns `None` if the terminal doesn't reply in time. Input that arrives in the meantime is kept.
+    pub fn report_text_area_pixels(&mut self) -> Option<Size> {
+        self.query("\u{1b}[14t", |reply| match reply {
+            Reply::TextAreaPixels(size) => Some(*size),
+            _ => None,
+        })
+    }
+
+    /// Asks the terminal for the size of its text area in cells (XTWINOPS).
+    ///
+    /// See [`report_text_area_pixels`](Self::report_text_area_pixels) for when this is useful.
+    pub fn report_text_area_cells(&mut self) -> Option<Size> {
+        self.query("\u{1b}[18t", |reply| match reply {
+            Reply::TextAreaCells(size) => Some(*size),
+            _ => None,
+        })
+    }
+
+    /// Writes the escape sequence `query` right away and waits for a reply for which `f` returns `Some`.
+    ///
+    /// Raw mode is enabled while waiting so that the reply is neither echoed nor held back until Enter is pressed.
+    fn query<T>(&mut self, query: &str, f: impl Fn(&Reply) -> Option<T>) -> Option<T> {
+        if !self.tty || !self.capabilities.escapes {
+            return None;
+        }
+        self.write_escape(query);
+        Write::flush(self).ok()?;
+
+        let raw_mode = self.raw_mode;
+        if !raw_mode {
+            self.enable_raw_mode();
+        }
+
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut input = INPUT.lock().unwrap();
+        let reply = loop {
+            if let Some(reply) = input.take_reply(&f) {
+                break Some(reply);
+            }
+            let timeout = match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) => timeout,
+                None => break None,
+            };
+            if wait_for_input(libc::STDIN_FILENO, Some(timeout)) {
+                let mut buffer = [0; 1024];
+                let count = read(libc::STDIN_FILENO, &mut buffer).unwrap_or(0);
+                input.feed(&buffer[..count]);
+            }
+        };
+        drop(input);
+
+        if !raw_mode {
+            self.disable_raw_mode();
+        }
+        reply
+    }
+
+    /// Sets the cursor to the top left corner.
+    pub fn reset_cursor(&mut self) {
+        self.write_escape("\u{1b}[;H");
+        self.cursor = Some(Point::default());
+    }
+
+    /// Sets the cursor to `point`.
+    ///
+    /// If possible, try to use the `move_cursor_{}_by` and `move_cursor_{}` methods instead for single operations.
+    pub fn set_cursor(&mut self, point: Point) {
+        self.write_escape(&format!("\u{1b}[{};{}H", point.y + 1, point.x + 1));
+        self.cursor = Some(point);
+    }
+
+    /// Sets the cursor X-coordinate to `x`.
+    pub fn set_cursor_x(&mut self, x: u16) {
+        self.write_escape(&format!("\u{1b}[{}G", x + 1));
+        if let Some(cursor) = &mut self.cursor {
+            cursor.x = x;
+        }
+    }
+
+    /// Sets the cursor Y-coordinate to `y`.
+    pub fn set_cursor_y(&mut self, y: u16) {
+        self.write_escape(&format!("\u{1b}[{}d", y + 1));
+        if let Some(cursor) = &mut self.cursor {
+            cursor.y = y;
+        }
+    }
+
+    /// Moves the cursor up by `cells`, but not past the top of the terminal.
+    pub fn move_cursor_up_by(&mut self, cells: u16) {
+        let cells = self.clamp_cursor_move(Direction::Up, cells);
+        if cells != 0 {
+            self.write_escape(&format!("\u{1b}[{}A", cells));
+        }
+    }
+    /// Moves the cursor down by `cells`, but not past the bottom of the terminal.
+    pub fn move_cursor_down_by(&mut self, cells: u16) {
+        let cells = self.clamp_cursor_move(Direction::Down, cells);
+        if cells != 0 {
+            self.write_escape(&format!("\u{1b}[{}B", cells));
+        }
+    }
+    /// Moves the cursor left by `cells`, but not past the left edge of the terminal.
+    pub fn move_cursor_left_by(&mut self, cells: u16) {
+        let cells = self.clamp_cursor_move(Direction::Left, cells);
+        if cells != 0 {
+            self.write_escape(&format!("\u{1b}[{}D", cells));
+        }
+    }
+    /// Moves the cursor right by `cells`, but not past the right edge of the terminal.
+    pub fn move_cursor_right_by(&mut self, cells: u16) {
+        let cells = self.clamp_cursor_move(Direction::Right, cells);
+        if cells != 0 {
+            self.write_escape(&format!("\u{1b}[{}C", cells));
+        }
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        self.write_escape("\u{1b}[A");
+        self.clamp_cursor_move(Direction::Up, 1);
+    }
+    pub fn move_cursor_down(&mut self) {
+        self.write_escape("\u{1b}[B");
+        self.clamp_cursor_move(Direction::Down, 1);
+    }
+    pub fn move_cursor_left(&mut self) {
+        self.write_escape("\u{1b}[D");
+        self.clamp_cursor_move(Direction::Left, 1);
+    }
+    pub fn move_cursor_right(&mut self) {
+        self.write_escape("\u{1b}[C");
+        self.clamp_cursor_move(Direction::Right, 1);
+    }
+
+    pub fn next_line(&mut self) {
+        self.write_escape("\u{1b}[E");
+        self.track_line_move(Direction::Down);
+    }
+    pub fn previous_line(&mut self) {
+        self.write_escape("\u{1b}[F");
+        self.track_line_move(Direction::Up);
+    }
+
+    pub fn save_cursor_point(&mut self) {
+        self.write_escape("\u{1b}7");
+        self.saved_cursor = self.cursor;
+    }
+    pub fn restore_cursor_point(&mut self) {
+        self.write_escape("\u{1b}8");
+        self.cursor = self.saved_cursor;
+    }
+
+    /// Sets the foreground color of the following text.
+    ///
+    /// Nothing is written if the color is already set or if colors are disabled.
+    pub fn set_foreground_color(&mut self, color: Color) {
+        if self.colors_enabled() && self.foreground_color != Some(color) {
+            self.write_escape(&format!(
+                "\u{1b}[{}m",
+                color.sgr(38, self.capabilities.color_depth)
+            ));
+            self.foreground_color = Some(color);
+        }
+    }
+    /// Sets the background color of the following text.
+    ///
+    /// Nothing is written if the color is already set or if colors are disabled.
+    pub fn set_background_color(&mut self, color: Color) {
+        if self.colors_enabled() && self.background_color != Some(color) {
+            self.write_escape(&format!(
+                "\u{1b}[{}m",
+                color.sgr(48, self.capabilities.color_depth)
+            ));
+            self.background_color = Some(color);
+        }
+    }
+
+    pub fn enable_italic(&mut self) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape("\u{1b}[3m");
+        self.attributes.insert(Attribute::Italic);
+    }
+    pub fn disable_italic(&mut self) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape("\u{1b}[23m");
+        self.attributes.remove(Attribute::Italic);
+    }
+
+    /// Resets the colors and attributes.
+    ///
+    /// Note that this resets the attributes, such as bold, as well. To only reset a color, use
+    /// [`reset_foreground_color_only`](Self::reset_foreground_color_only) or
+    /// [`reset_background_color_only`](Self::reset_background_color_only).
+    pub fn reset_colors(&mut self) {
+        if !self.capabilities.escapes {
+            return;
+        }
+        self.write_escape("\u{1b}[0m");
+        self.foreground_color = None;
+        self.background_color = None;
+        self.attributes = Attributes::empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.write_escape("\u{1b}[2J");
+        self.buffer.invalidate();
+    }
+    pub fn clear_from_cursor_to_end(&mut self) {
+        self.write_escape("\u{1b}[1J");
+        self.buffer.invalidate();
+    }
+
+    /// Queries the size of the terminal.
+    ///
+    /// Prefer [`size`](Self::size), which returns the size that is kept up to date by [`read_event`](Self::read_event).
+    pub fn query_size() -> io::Result<Size> {
+        let size = window_size()?;
+        Ok(Size {
+            width: size.ws_col,
+            height: size.ws_row,
+        })
+    }
+
+    pub(crate) fn is_tty(_stdout: &io::StdoutLock) -> bool {
+        // SAFETY: `isatty` has no preconditions
+        unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+    }
+}
+
+fn window_size() -> io::Result<libc::winsize> {
+    // SAFETY: `winsize` is a plain struct of integers
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+    // SAFETY: `TIOCGWINSZ` writes into the `winsize` it is given
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size)
+}
+
+/// Returns the size of a cell in pixels, or zero if unknown.
+fn cell_pixel_size() -> Size {
+    match window_size() {
+        Ok(size) => Size {
+            width: size.ws_xpixel.checked_div(size.ws_col).unwrap_or(0),
+            height: size.ws_ypixel.checked_div(size.ws_row).unwrap_or(0),
+        },
+        Err(_) => Size::default(),
+    }
+}
+
+fn get_mode(fd: RawFd) -> io::Result<libc::termios> {
+    // SAFETY: `termios` is a plain struct of integers
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    // SAFETY: `tcgetattr` writes into the `termios` it is given
+    if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(termios)
+}
+
+fn set_mode(fd: RawFd, termios: &libc::termios) -> io::Result<()> {
+    // SAFETY: `tcsetattr` only reads the `termios` it is given
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn enable_raw_mode(fd: RawFd) -> io::Result<()> {
+    let mut original_mode = ORIGINAL_MODE.lock().unwrap();
+    if original_mode.is_some() {
+        return Ok(());
+    }
+
+    let mode = get_mode(fd)?;
+    let mut raw_mode = mode;
+    // SAFETY: `cfmakeraw` only modifies the `termios` it is given
+    unsafe { libc::cfmakeraw(&mut raw_mode) };
+    set_mode(fd, &raw_mode)?;
+
+    *original_mode = Some(mode);
+    Ok(())
+}
+
+fn disable_raw_mode(fd: RawFd) -> io::Result<()> {
+    if let Some(mode) = ORIGINAL_MODE.lock().unwrap().take() {
+        set_mode(fd, &mode)?;
+    }
+    Ok(())
+}
+
+/// Makes [`RESIZED`] be set when the terminal is resized.
+fn handle_resizes() {
+    extern "C" fn handle_resize(_: libc::c_int) {
+        RESIZED.store(true, Ordering::Relaxed);
+    }
+
+    // SAFETY: `sigaction` is a plain struct of integers and pointers
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = handle_resize as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only does an atomic store, which is async-signal-safe
+    unsafe { libc::sigaction(libc::SIGWINCH, &action, std::ptr::null_mut()) };
+}
+
+/// Waits until `fd` can be read from, at most for `timeout`, or forever if it's `None`.
+///
+/// Returns `false` if the time ran out or if the waiting was interrupted by a signal.
+fn wait_for_input(fd: RawFd, timeout: Option<Duration>) -> bool {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.map_or(-1, |timeout| {
+        timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int
+    });
+    // SAFETY: a single valid `pollfd` is passed
+    unsafe { libc::poll(&mut poll_fd, 1, timeout) > 0 }
+}
+
+fn read(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    // SAFETY: at most `buffer.len()` bytes are written into `buffer`
+    let count = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+    if count == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    /// Opens a pseudoterminal and returns the file descriptor of its terminal side.
+    fn pseudoterminal() -> RawFd {
+        // SAFETY: the functions are called in the documented order with the file descriptor they return
+        unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert_ne!(master, -1);
+            assert_eq!(libc::grantpt(master), 0);
+            assert_eq!(libc::unlockpt(master), 0);
+            let name = CStr::from_ptr(libc::ptsname(master));
+            let slave = libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY);
+            assert_ne!(slave, -1);
+            slave
+        }
+    }
+
+    #[test]
+    fn test_raw_mode() {
+        let fd = pseudoterminal();
+        let mode = get_mode(fd).unwrap();
+        assert_ne!(mode.c_lflag & (libc::ICANON | libc::ECHO), 0);
+
+        enable_raw_mode(fd).unwrap();
+        let raw_mode = get_mode(fd).unwrap();
+        assert_eq!(
+            raw_mode.c_lflag & (libc::ICANON | libc::ECHO | libc::ISIG),
+            0
+        );
+
+        // Enabling it again must not overwrite the original mode
+        enable_raw_mode(fd).unwrap();
+
+        disable_raw_mode(fd).unwrap();
+        let restored_mode = get_mode(fd).unwrap();
+        assert_eq!(restored_mode.c_lflag, mode.c_lflag);
+        assert_eq!(restored_mode.c_iflag, mode.c_iflag);
+        assert_eq!(restored_mode.c_oflag, mode.c_oflag);
+    }
+
+    #[test]
+    fn test_set_cursor() {
+        let mut terminal = Terminal::test(Size {
+            width: 80,
+            height: 24,
+        });
+        terminal.set_cursor(Point { x: 0, y: 2 });
+        terminal.set_foreground_color(Color::Byte(100));
+        assert_eq!(terminal.output(), "\u{1b}[3;1H\u{1b}[38;5;100m");
+    }
+}
@@ -1,4 +1,9 @@
-use std::{convert::TryFrom, fmt};
+use std::{
+    convert::TryFrom,
+    fmt,
+    time::{Duration, Instant},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
 pub struct Point {
@@ -35,7 +40,19 @@ impl Size {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+/// A rectangular area of the terminal with its top left corner at `point`.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub struct Rect {
+    pub point: Point,
+    pub size: Size,
+}
+
+/// A color.
+///
+/// Of the 4-bit colors, the ones prefixed with `Dark` as well as [`Black`](Color::Black) and [`Gray`](Color::Gray)
+/// are the normal colors 0 to 7, while the others are their bright variants 8 to 15,
+/// which are also available under clearer names such as [`Color::BRIGHT_RED`] for [`Color::Red`].
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
 pub enum Color {
     // 4-bit colors
     DarkRed,
@@ -45,6 +62,7 @@ pub enum Color {
     DarkMagenta,
     DarkCyan,
     /// A terminal's default background color.
+    #[default]
     Black,
     /// A terminal's default foreground color.
     Gray,
@@ -66,16 +84,20 @@ pub enum Color {
     },
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::Black
-    }
-}
-
 impl Color {
     pub const GRAYSCALE_COLOR_COUNT: u8 = 24;
     pub const FOUR_BIT_COLOR_COUNT: u8 = 8 * 2;
 
+    // The bright variants of the 4-bit colors
+    pub const BRIGHT_BLACK: Color = Color::DarkGray;
+    pub const BRIGHT_RED: Color = Color::Red;
+    pub const BRIGHT_GREEN: Color = Color::Green;
+    pub const BRIGHT_YELLOW: Color = Color::Yellow;
+    pub const BRIGHT_BLUE: Color = Color::Blue;
+    pub const BRIGHT_MAGENTA: Color = Color::Magenta;
+    pub const BRIGHT_CYAN: Color = Color::Cyan;
+    pub const BRIGHT_WHITE: Color = Color::White;
+
     pub fn invert(&self) -> Self {
         use Color::*;
 
@@ -99,6 +121,178 @@ impl Color {
         }
     }
 
+    /// Returns the red, green and blue components of this color.
+    ///
+    /// 4-bit and 8-bit colors are resolved using xterm's default palette,
+    /// which means that the result may differ from what the terminal actually displays.
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        use Color::*;
+
+        const FOUR_BIT_COLORS: [(u8, u8, u8); Color::FOUR_BIT_COLOR_COUNT as usize] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        let byte = match self {
+            Rgb { r, g, b } => return (*r, *g, *b),
+            color => color.palette_index().unwrap(),
+        };
+
+        if byte < Color::FOUR_BIT_COLOR_COUNT {
+            FOUR_BIT_COLORS[byte as usize]
+        } else if byte > u8::MAX - Color::GRAYSCALE_COLOR_COUNT {
+            let level = 8 + 10 * (byte - (u8::MAX - Color::GRAYSCALE_COLOR_COUNT + 1));
+            (level, level, level)
+        } else {
+            let index = byte - Color::FOUR_BIT_COLOR_COUNT;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+    }
+
+    /// Returns the relative luminance of this color as defined by WCAG, from 0 for black to 1 for white.
+    ///
+    /// 4-bit and 8-bit colors are resolved using [`to_rgb`](Self::to_rgb).
+    pub fn luminance(&self) -> f32 {
+        fn linearize(component: u8) -> f32 {
+            let component = component as f32 / u8::MAX as f32;
+            if component <= 0.03928 {
+                component / 12.92
+            } else {
+                ((component + 0.055) / 1.055).powf(2.4)
+            }
+        }
+
+        let (r, g, b) = self.to_rgb();
+        0.2126 * linearize(r) + 0.7152 * linearize(g) + 0.0722 * linearize(b)
+    }
+
+    /// Returns the contrast ratio between this color and `other` as defined by WCAG,
+    /// from 1 for equal colors to 21 for black and white.
+    pub fn contrast_ratio(&self, other: &Color) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Returns either black or white, whichever is more readable on the background `bg`.
+    ///
+    /// The colors are returned as RGB colors because the terminal might not display
+    /// [`Color::Black`] and [`Color::White`] as actual black and white.
+    pub fn readable_on(bg: Color) -> Color {
+        let black = Color::Rgb { r: 0, g: 0, b: 0 };
+        let white = Color::Rgb {
+            r: u8::MAX,
+            g: u8::MAX,
+            b: u8::MAX,
+        };
+        if black.contrast_ratio(&bg) >= white.contrast_ratio(&bg) {
+            black
+        } else {
+            white
+        }
+    }
+
+    /// Returns the index of this color in the 256-color palette or `None` if it's an RGB color.
+    pub(crate) fn palette_index(&self) -> Option<u8> {
+        use Color::*;
+
+        Some(match self {
+            Rgb { .. } => return None,
+            Byte(byte) => *byte,
+            Black => 0,
+            DarkRed => 1,
+            DarkGreen => 2,
+            DarkYellow => 3,
+            DarkBlue => 4,
+            DarkMagenta => 5,
+            DarkCyan => 6,
+            Gray => 7,
+            DarkGray => 8,
+            Red => 9,
+            Green => 10,
+            Yellow => 11,
+            Blue => 12,
+            Magenta => 13,
+            Cyan => 14,
+            White => 15,
+        })
+    }
+
+    /// Returns the parameters of an SGR sequence setting this color, downgraded to `depth`,
+    /// for the foreground if `layer` is 38 or for the background if it's 48.
+    pub(crate) fn sgr(&self, layer: u8, depth: ColorDepth) -> String {
+        match self.to_depth(depth) {
+            Color::Rgb { r, g, b } => format!("{};2;{};{};{}", layer, r, g, b),
+            // 16-color terminals only understand the parameters 30 to 37 and 90 to 97 (or 40 to 47 and 100 to 107)
+            color if depth <= ColorDepth::Ansi16 => match color.palette_index().unwrap() {
+                index @ 0..8 => (layer - 8 + index).to_string(),
+                index => (layer + 52 + index - 8).to_string(),
+            },
+            color => format!("{};5;{}", layer, color.palette_index().unwrap()),
+        }
+    }
+
+    /// Returns the color closest to this one that a terminal with `depth` can display.
+    ///
+    /// RGB colors are reduced to the closest 8-bit color and 8-bit colors to the closest 4-bit color,
+    /// using xterm's default palette. For [`ColorDepth::Monochrome`], this is the closest 4-bit color.
+    pub fn to_depth(&self, depth: ColorDepth) -> Color {
+        match (depth, self) {
+            (ColorDepth::TrueColor, color) => *color,
+            (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => nearest_ansi256(*r, *g, *b),
+            (ColorDepth::Ansi256, color) => *color,
+            (ColorDepth::Ansi16 | ColorDepth::Monochrome, color) => match color.palette_index() {
+                Some(index) if index < Color::FOUR_BIT_COLOR_COUNT => {
+                    FOUR_BIT_COLORS[index as usize]
+                }
+                _ => {
+                    let rgb = color.to_rgb();
+                    *FOUR_BIT_COLORS
+                        .iter()
+                        .min_by_key(|color| distance(color.to_rgb(), rgb))
+                        .unwrap()
+                }
+            },
+        }
+    }
+
+    /// Returns the color of the 256-color palette's 6×6×6 color cube at the given coordinates.
+    ///
+    /// Each component must be in the range `0..=5`.
+    pub fn ansi256_cube(r: u8, g: u8, b: u8) -> Self {
+        assert!(r < 6 && g < 6 && b < 6, "color cube component out of range");
+        Color::Byte(Color::FOUR_BIT_COLOR_COUNT + 36 * r + 6 * g + b)
+    }
+
+    /// Returns the color of the 256-color palette's grayscale ramp at `level`, from dark to light.
+    ///
+    /// `level` must be in the range `0..=23`.
+    pub fn ansi256_gray(level: u8) -> Self {
+        assert!(
+            level < Color::GRAYSCALE_COLOR_COUNT,
+            "grayscale level out of range"
+        );
+        Color::Byte(u8::MAX - Color::GRAYSCALE_COLOR_COUNT + 1 + level)
+    }
+
     /// Tries to parse the input into an RGB color.
     /// It can parse the following RGB notations:
     ///
@@ -184,6 +378,373 @@ impl Color {
     }
 }
 
+/// The 4-bit colors in the order of their indices in the 256-color palette.
+const FOUR_BIT_COLORS: [Color; Color::FOUR_BIT_COLOR_COUNT as usize] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+/// Returns the squared distance between two colors.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let component = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    component(r1, r2) + component(g1, g2) + component(b1, b2)
+}
+
+/// Returns the color of the 256-color palette's color cube or grayscale ramp that is closest to the given one.
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> Color {
+    // The levels of the cube are 0, 95, 135, 175, 215 and 255
+    let level = |component: u8| match component {
+        0..48 => 0,
+        48..115 => 1,
+        component => (component - 35) / 40,
+    };
+    let cube = Color::ansi256_cube(level(r), level(g), level(b));
+    // The levels of the ramp are 8, 18, ..., 238
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = Color::ansi256_gray((average.saturating_sub(3) / 10).min(23) as u8);
+
+    if distance(cube.to_rgb(), (r, g, b)) <= distance(gray.to_rgb(), (r, g, b)) {
+        cube
+    } else {
+        gray
+    }
+}
+
+/// How many colors a terminal can display.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ColorDepth {
+    /// No colors, such as with `TERM=dumb`.
+    Monochrome,
+    /// The 4-bit colors.
+    Ansi16,
+    /// The 8-bit colors.
+    Ansi256,
+    /// The 24-bit RGB colors.
+    TrueColor,
+}
+
+/// Which escape sequences a terminal understands.
+/// See [`Terminal::capabilities`](crate::Terminal::capabilities).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Capabilities {
+    /// Whether escape sequences are understood at all, including the ones moving the cursor, clearing and
+    /// setting colors and attributes.
+    pub escapes: bool,
+    /// The colors that can be displayed.
+    pub color_depth: ColorDepth,
+    /// Whether the previous character can be repeated using REP (`CSI n b`).
+    pub rep: bool,
+}
+
+/// The shape of the cursor.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
+
+/// The size of the characters of a line, set using [`Terminal::set_line_size`](crate::Terminal::set_line_size).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub enum LineSize {
+    #[default]
+    Normal,
+    /// Characters twice as wide (DECDWL).
+    DoubleWidth,
+    /// The top half of characters twice as wide and twice as high (DECDHL).
+    /// The same text has to be written on the line below with [`DoubleHeightBottom`](Self::DoubleHeightBottom).
+    DoubleHeightTop,
+    /// The bottom half of characters twice as wide and twice as high (DECDHL).
+    DoubleHeightBottom,
+}
+
+/// The lines of a box drawn using [`Terminal::draw_box`](crate::Terminal::draw_box).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub enum BoxStyle {
+    /// `┌─┐`
+    #[default]
+    Light,
+    /// `┏━┓`
+    Heavy,
+    /// `╔═╗`
+    Double,
+    /// `╭─╮`
+    Rounded,
+}
+
+impl BoxStyle {
+    /// Returns the horizontal line, the vertical line and the top left, top right, bottom left and bottom right corners.
+    pub(crate) fn chars(&self) -> [char; 6] {
+        match self {
+            BoxStyle::Light => ['─', '│', '┌', '┐', '└', '┘'],
+            BoxStyle::Heavy => ['━', '┃', '┏', '┓', '┗', '┛'],
+            BoxStyle::Double => ['═', '║', '╔', '╗', '╚', '╝'],
+            BoxStyle::Rounded => ['─', '│', '╭', '╮', '╰', '╯'],
+        }
+    }
+}
+
+/// A text attribute.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Attribute {
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    Blinking,
+    /// Swaps the foreground and background colors.
+    Reversed,
+    Hidden,
+    CrossedOut,
+}
+
+impl Attribute {
+    pub const ALL: [Attribute; 8] = [
+        Attribute::Bold,
+        Attribute::Dim,
+        Attribute::Italic,
+        Attribute::Underlined,
+        Attribute::Blinking,
+        Attribute::Reversed,
+        Attribute::Hidden,
+        Attribute::CrossedOut,
+    ];
+
+    /// Returns the SGR parameter enabling this attribute.
+    pub(crate) fn sgr(&self) -> u8 {
+        match self {
+            Attribute::Bold => 1,
+            Attribute::Dim => 2,
+            Attribute::Italic => 3,
+            Attribute::Underlined => 4,
+            Attribute::Blinking => 5,
+            Attribute::Reversed => 7,
+            Attribute::Hidden => 8,
+            Attribute::CrossedOut => 9,
+        }
+    }
+
+    /// Returns the SGR parameter disabling this attribute.
+    ///
+    /// Note that bold and dim are disabled by the same parameter.
+    pub(crate) fn reset_sgr(&self) -> u8 {
+        match self {
+            Attribute::Bold | Attribute::Dim => 22,
+            attribute => 20 + attribute.sgr(),
+        }
+    }
+}
+
+/// A set of attributes.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    fn bit(attribute: Attribute) -> u8 {
+        1 << attribute as u8
+    }
+
+    pub fn contains(&self, attribute: Attribute) -> bool {
+        self.0 & Self::bit(attribute) != 0
+    }
+
+    pub fn insert(&mut self, attribute: Attribute) {
+        self.0 |= Self::bit(attribute);
+    }
+
+    pub fn remove(&mut self, attribute: Attribute) {
+        self.0 &= !Self::bit(attribute);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Attribute> + '_ {
+        Attribute::ALL
+            .iter()
+            .copied()
+            .filter(move |attribute| self.contains(*attribute))
+    }
+}
+
+impl From<&[Attribute]> for Attributes {
+    fn from(attributes: &[Attribute]) -> Self {
+        let mut set = Self::empty();
+        for attribute in attributes {
+            set.insert(*attribute);
+        }
+        set
+    }
+}
+
+/// The colors and attributes of text.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash, Debug)]
+pub struct Style {
+    /// The foreground color, or `None` for the terminal's default or if it's unknown.
+    pub fg: Option<Color>,
+    /// The background color, or `None` for the terminal's default or if it's unknown.
+    pub bg: Option<Color>,
+    pub attributes: Attributes,
+}
+
+/// Limits how often a loop, such as a render loop, runs per second so that it doesn't busy-spin.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameLimiter {
+    frame_time: Duration,
+    last_tick: Instant,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter for `target_fps` frames per second, which must not be zero.
+    pub fn new(target_fps: u32) -> Self {
+        assert!(target_fps != 0, "target FPS must not be zero");
+        Self {
+            frame_time: Duration::from_secs(1) / target_fps,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Sleeps for the rest of the current frame and returns the time since the last tick,
+    /// or since the creation of this limiter if this is the first tick.
+    ///
+    /// If the frame took longer than it should have, this does not sleep at all
+    /// so that the next frame can start right away.
+    pub fn tick(&mut self) -> Duration {
+        let (sleep, delta) = self.tick_at(Instant::now());
+        if !sleep.is_zero() {
+            std::thread::sleep(sleep);
+        }
+        delta
+    }
+
+    /// Starts the next frame as if ticking at `now` and returns how long to sleep until then
+    /// and the time since the last tick.
+    fn tick_at(&mut self, now: Instant) -> (Duration, Duration) {
+        let elapsed = now.saturating_duration_since(self.last_tick);
+        let sleep = self.frame_time.saturating_sub(elapsed);
+        self.last_tick = now + sleep;
+        (sleep, elapsed + sleep)
+    }
+}
+
+/// Returns the name of the terminal that commonly replies with the type `code`
+/// to a query of the secondary device attributes, such as [`Terminal::terminal_identity`](crate::Terminal::terminal_identity).
+///
+/// Many terminals reply with the type of a DEC terminal they emulate, so the name is only a hint.
+pub fn terminal_type_name(code: u16) -> Option<&'static str> {
+    Some(match code {
+        0 => "VT100",
+        1 => "VT220",
+        2 => "VT240",
+        18 => "VT330",
+        19 => "VT340",
+        24 => "VT320",
+        41 => "VT420",
+        61 => "VT510",
+        64 => "VT520",
+        65 => "VT525",
+        77 => "mintty",
+        83 => "screen",
+        84 => "tmux",
+        85 => "rxvt-unicode",
+        _ => return None,
+    })
+}
+
+/// Text scrolling through a window of a fixed width like a marquee, e.g. in a status bar.
+///
+/// Text that fits into the window doesn't scroll.
+#[derive(Clone, Debug)]
+pub struct Ticker {
+    text: String,
+    /// Put between the end of the text and its start scrolling in again.
+    separator: String,
+    width: u16,
+    /// By how many columns the text has scrolled.
+    offset: usize,
+}
+
+impl Ticker {
+    /// Creates a ticker showing `text` in a window `width` columns wide, with `separator` between the repetitions.
+    pub fn new(text: &str, separator: &str, width: u16) -> Self {
+        Self {
+            text: text.to_string(),
+            separator: separator.to_string(),
+            width,
+            offset: 0,
+        }
+    }
+
+    /// Returns whether the text is too wide to fit into the window.
+    fn scrolls(&self) -> bool {
+        self.text.width() > self.width as usize
+    }
+
+    /// Scrolls the text to the left by a column, starting over once all of it and the separator have passed by.
+    pub fn advance(&mut self) {
+        if self.scrolls() {
+            self.offset = (self.offset + 1) % (self.text.width() + self.separator.width());
+        }
+    }
+
+    /// Returns what is currently visible in the window, which is always exactly as wide as the window
+    /// unless the text doesn't scroll.
+    ///
+    /// If a wide character is only partially visible, its visible half is a space.
+    pub fn render(&self) -> String {
+        if !self.scrolls() {
+            return self.text.clone();
+        }
+
+        let width = self.width as usize;
+        let mut rendered = String::new();
+        let mut rendered_width = 0;
+        let mut column = 0;
+        for char in self.text.chars().chain(self.separator.chars()).cycle() {
+            let char_width = char.width().unwrap_or(0);
+            let start = column;
+            column += char_width;
+            if start < self.offset && column <= self.offset {
+                continue;
+            }
+            if start < self.offset {
+                // Only the second half of a wide character is visible
+                rendered_width += column - self.offset;
+                rendered.extend(std::iter::repeat_n(' ', column - self.offset));
+            } else if rendered_width + char_width > width {
+                rendered.extend(std::iter::repeat_n(' ', width - rendered_width));
+                break;
+            } else {
+                rendered_width += char_width;
+                rendered.push(char);
+            }
+            if rendered_width == width {
+                break;
+            }
+        }
+        rendered
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -219,6 +780,123 @@ mod tests {
         // assert_eq!(parse("255,255,255efefef"), rgb(255, 255, 255));
     }
 
+    #[test]
+    fn test_ansi256_cube() {
+        assert_eq!(Color::ansi256_cube(0, 0, 0), Color::Byte(16));
+        assert_eq!(Color::ansi256_cube(5, 0, 0), Color::Byte(196));
+        assert_eq!(Color::ansi256_cube(0, 5, 0), Color::Byte(46));
+        assert_eq!(Color::ansi256_cube(0, 0, 5), Color::Byte(21));
+        assert_eq!(Color::ansi256_cube(5, 5, 5), Color::Byte(231));
+    }
+
+    #[test]
+    fn test_ansi256_gray() {
+        assert_eq!(Color::ansi256_gray(0), Color::Byte(232));
+        assert_eq!(Color::ansi256_gray(12), Color::Byte(244));
+        assert_eq!(Color::ansi256_gray(23), Color::Byte(255));
+    }
+
+    #[test]
+    fn test_bright_colors() {
+        assert_eq!(
+            [
+                Color::BRIGHT_BLACK,
+                Color::BRIGHT_RED,
+                Color::BRIGHT_GREEN,
+                Color::BRIGHT_YELLOW,
+                Color::BRIGHT_BLUE,
+                Color::BRIGHT_MAGENTA,
+                Color::BRIGHT_CYAN,
+                Color::BRIGHT_WHITE,
+            ]
+            .iter()
+            .map(|color| color.palette_index().unwrap())
+            .collect::<Vec<_>>(),
+            (8..16).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_to_rgb() {
+        assert_eq!(Color::Red.to_rgb(), (255, 0, 0));
+        assert_eq!(Color::DarkBlue.to_rgb(), (0, 0, 238));
+        assert_eq!(Color::ansi256_cube(5, 0, 1).to_rgb(), (255, 0, 95));
+        assert_eq!(Color::ansi256_gray(23).to_rgb(), (238, 238, 238));
+        assert_eq!(Color::Rgb { r: 1, g: 2, b: 3 }.to_rgb(), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_to_depth() {
+        let orange = Color::Rgb {
+            r: 255,
+            g: 135,
+            b: 0,
+        };
+        assert_eq!(orange.to_depth(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.to_depth(ColorDepth::Ansi256), Color::Byte(208));
+        assert_eq!(orange.to_depth(ColorDepth::Ansi16), Color::DarkYellow);
+        assert_eq!(
+            Color::Rgb {
+                r: 100,
+                g: 100,
+                b: 100
+            }
+            .to_depth(ColorDepth::Ansi256),
+            Color::ansi256_gray(9)
+        );
+        assert_eq!(
+            Color::Byte(208).to_depth(ColorDepth::Ansi16),
+            Color::DarkYellow
+        );
+        assert_eq!(Color::Byte(4).to_depth(ColorDepth::Ansi16), Color::DarkBlue);
+        assert_eq!(Color::Cyan.to_depth(ColorDepth::Ansi16), Color::Cyan);
+
+        assert_eq!(Color::Red.sgr(38, ColorDepth::Ansi256), "38;5;9");
+        assert_eq!(Color::Red.sgr(38, ColorDepth::Ansi16), "91");
+        assert_eq!(Color::DarkRed.sgr(48, ColorDepth::Ansi16), "41");
+        assert_eq!(orange.sgr(48, ColorDepth::Ansi16), "43");
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let black = Color::Rgb { r: 0, g: 0, b: 0 };
+        let white = Color::Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        assert_eq!(black.luminance(), 0.0);
+        assert!((white.luminance() - 1.0).abs() < 0.001);
+        assert!((black.contrast_ratio(&white) - 21.0).abs() < 0.01);
+        assert!((white.contrast_ratio(&black) - 21.0).abs() < 0.01);
+        assert_eq!(Color::Red.contrast_ratio(&Color::Red), 1.0);
+        // #777777 on white is a well-known borderline case at about 4.48
+        let gray = Color::Rgb {
+            r: 0x77,
+            g: 0x77,
+            b: 0x77,
+        };
+        assert!((gray.contrast_ratio(&white) - 4.48).abs() < 0.01);
+
+        assert_eq!(Color::readable_on(white), black);
+        assert_eq!(Color::readable_on(Color::Yellow), black);
+        assert_eq!(Color::readable_on(black), white);
+        assert_eq!(Color::readable_on(Color::DarkBlue), white);
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut attributes = Attributes::from(&[Attribute::Bold, Attribute::CrossedOut][..]);
+        assert!(attributes.contains(Attribute::Bold));
+        assert!(!attributes.contains(Attribute::Dim));
+        attributes.remove(Attribute::Bold);
+        attributes.insert(Attribute::Italic);
+        assert_eq!(
+            attributes.iter().collect::<Vec<_>>(),
+            [Attribute::Italic, Attribute::CrossedOut]
+        );
+    }
+
     #[test]
     fn test_parse_hex() {
         fn parse(string: &str) -> Option<Color> {
@@ -228,4 +906,74 @@ mod tests {
         assert_eq!(parse("dea584"), rgb(222, 165, 132));
         assert_eq!(parse("ff0000"), rgb(255, 0, 0));
     }
+
+    #[test]
+    fn test_terminal_type_name() {
+        assert_eq!(terminal_type_name(0), Some("VT100"));
+        assert_eq!(terminal_type_name(84), Some("tmux"));
+        assert_eq!(terminal_type_name(1000), None);
+    }
+
+    #[test]
+    fn test_ticker() {
+        let mut ticker = Ticker::new("abcde", " | ", 4);
+        let mut rendered = Vec::new();
+        for _ in 0..9 {
+            rendered.push(ticker.render());
+            ticker.advance();
+        }
+        assert_eq!(
+            rendered,
+            ["abcd", "bcde", "cde ", "de |", "e | ", " | a", "| ab", " abc", "abcd"]
+        );
+
+        // Text that fits doesn't scroll
+        let mut ticker = Ticker::new("abc", " ", 4);
+        ticker.advance();
+        assert_eq!(ticker.render(), "abc");
+    }
+
+    #[test]
+    fn test_ticker_wide_characters() {
+        let mut ticker = Ticker::new("世界a", " ", 4);
+        let mut rendered = Vec::new();
+        for _ in 0..7 {
+            rendered.push(ticker.render());
+            ticker.advance();
+        }
+        assert_eq!(
+            rendered,
+            ["世界", " 界a", "界a ", " a  ", "a 世", " 世 ", "世界"]
+        );
+    }
+
+    #[test]
+    fn test_frame_limiter() {
+        let frame_time = Duration::from_secs(1) / 60;
+        let start = Instant::now();
+        let mut limiter = FrameLimiter {
+            frame_time,
+            last_tick: start,
+        };
+
+        // The rest of the frame is slept
+        assert_eq!(
+            limiter.tick_at(start + frame_time / 4),
+            (frame_time - frame_time / 4, frame_time)
+        );
+        assert_eq!(
+            limiter.tick_at(start + frame_time),
+            (frame_time, frame_time)
+        );
+
+        // Behind schedule
+        assert_eq!(
+            limiter.tick_at(start + frame_time * 5),
+            (Duration::ZERO, frame_time * 3)
+        );
+        assert_eq!(
+            limiter.tick_at(start + frame_time * 5),
+            (frame_time, frame_time)
+        );
+    }
 }
@@ -0,0 +1,55 @@
+//! Checks that escape sequences are written without allocating.
+//!
+//! This is an integration test of its own because it replaces the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+use tanmatsu::{util::Size, Terminal};
+
+thread_local! {
+    /// How many allocations the current thread has made.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the allocations of each thread so that the test harness doesn't affect the count.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_osc_without_allocation() {
+    let mut terminal = Terminal::with_writer(
+        Vec::new(),
+        Size {
+            width: 80,
+            height: 24,
+        },
+    );
+
+    let allocations = ALLOCATIONS.with(Cell::get);
+    terminal.change_foreground_color("FF0000");
+    terminal.change_background_color("00FF00");
+    terminal.change_cursor_color("0000FF");
+    terminal.reset_palette_color(4);
+    assert_eq!(ALLOCATIONS.with(Cell::get), allocations);
+
+    terminal.flush();
+    assert_eq!(
+        terminal.stdout.get_ref(),
+        b"\x1b]10;#FF0000\x07\x1b]11;#00FF00\x07\x1b]12;#0000FF\x07\x1b]104;4\x07"
+    );
+}